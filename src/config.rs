@@ -6,16 +6,22 @@ use serde::de::{Deserializer, Error as SerdeError};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 use sscanf::scanf;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 use std::result::Result as StdResult;
 use std::str::{self, FromStr};
+use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tera::Tera;
 use tracing::Level;
 
+/// A [`Config`] shared between the event loop and the control socket, so that `runst msg
+/// config ...` overrides can be applied to the running daemon without a restart.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
 /// Environment variable for the configuration file.
 const CONFIG_ENV: &str = "RUNST_CONFIG";
 
@@ -42,6 +48,13 @@ pub struct Config {
 
 impl Config {
     /// Parses the configuration file.
+    ///
+    /// [`GlobalConfig`] and [`UrgencyConfig`] deserialize field-by-field: a malformed field
+    /// falls back to its built-in default (with a `tracing::warn!` naming the offending key)
+    /// rather than failing the whole parse, unknown keys are ignored, and a table that only
+    /// overrides a few fields is filled in with defaults for the rest. The `global`,
+    /// `urgency_low`, `urgency_normal` and `urgency_critical` tables themselves must all be
+    /// present, and a file that is not valid TOML fails the parse.
     pub fn parse() -> Result<Self> {
         for config_path in [
             env::var(CONFIG_ENV).ok().map(PathBuf::from),
@@ -66,7 +79,8 @@ impl Config {
             let config = toml::from_str(&embedded_config)?;
             Ok(config)
         } else {
-            Err(Error::Config(String::from("configuration file not found")))
+            tracing::warn!("embedded default configuration not found, using built-in defaults");
+            Ok(Self::default())
         }
     }
 
@@ -78,86 +92,525 @@ impl Config {
             Urgency::Critical => self.urgency_critical.clone(),
         }
     }
+
+    /// Applies a single `field.path=value` assignment (as produced by `runst msg config
+    /// ...`) onto this configuration in place, where `value` is parsed as a TOML fragment.
+    ///
+    /// The override replaces the current value until the daemon is restarted.
+    pub fn apply_override(&mut self, assignment: &str) -> Result<()> {
+        let (path, raw_value) = assignment.split_once('=').ok_or_else(|| {
+            Error::Config(format!(
+                "invalid override `{assignment}`, expected `field.path=value`"
+            ))
+        })?;
+
+        let wrapped = format!("value = {}", raw_value.trim());
+        let wrapped: toml::value::Table = toml::from_str(&wrapped)?;
+        let new_value = wrapped
+            .get("value")
+            .cloned()
+            .ok_or_else(|| Error::Config(format!("cannot parse value `{raw_value}`")))?;
+
+        let mut document = toml::Value::try_from(&*self)?;
+        // `GlobalConfig::log_verbosity` carries `#[serde(skip_serializing)]` (`Level` has no
+        // `Serialize` impl of its own), so it's missing from `document` here; reinsert it
+        // manually so an unrelated override doesn't reset it back to the default on the
+        // round trip through `GlobalConfig`'s custom `Deserialize`. `geometry` serializes as
+        // a table but is read back as a `WIDTHxHEIGHT{+-}X{+-}Y` string, so it is rewritten
+        // in that form for the same reason.
+        if let Some(global) = document.get_mut("global").and_then(|v| v.as_table_mut()) {
+            global.insert(
+                String::from("log_verbosity"),
+                toml::Value::String(self.global.log_verbosity.to_string()),
+            );
+            let geometry = &self.global.geometry;
+            global.insert(
+                String::from("geometry"),
+                toml::Value::String(format!(
+                    "{}x{}{}{}{}{}",
+                    geometry.width,
+                    geometry.height,
+                    if geometry.x_negative { '-' } else { '+' },
+                    geometry.x,
+                    if geometry.y_negative { '-' } else { '+' },
+                    geometry.y
+                )),
+            );
+        }
+        // A custom command's `filter` serializes as a table but is read back as a JSON string,
+        // so rewrite it in that form to keep `custom_commands` across the round trip.
+        for (key, urgency) in [
+            ("urgency_low", &self.urgency_low),
+            ("urgency_normal", &self.urgency_normal),
+            ("urgency_critical", &self.urgency_critical),
+        ] {
+            let values = document
+                .get_mut(key)
+                .and_then(|v| v.get_mut("custom_commands"))
+                .and_then(|v| v.as_array_mut());
+            let commands = urgency.custom_commands.iter().flatten();
+            for (command, value) in commands.zip(values.into_iter().flatten()) {
+                if let (Some(filter), Some(table)) = (&command.filter, value.as_table_mut()) {
+                    let filter = toml::Value::String(serde_json::to_string(filter)?);
+                    table.insert(String::from("filter"), filter);
+                }
+            }
+        }
+        set_path(&mut document, &path.split('.').collect::<Vec<_>>(), new_value)?;
+        *self = document.try_into()?;
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            global: GlobalConfig::default(),
+            urgency_low: UrgencyConfig::default(),
+            urgency_normal: UrgencyConfig::default(),
+            urgency_critical: UrgencyConfig::default(),
+        }
+    }
+}
+
+/// Sets the value at `path` (a sequence of table keys) within a parsed TOML document,
+/// creating intermediate tables as needed.
+fn set_path(value: &mut toml::Value, path: &[&str], new_value: toml::Value) -> Result<()> {
+    let (field, rest) = path
+        .split_first()
+        .ok_or_else(|| Error::Config(String::from("empty override path")))?;
+    let table = value
+        .as_table_mut()
+        .ok_or_else(|| Error::Config(format!("`{field}` is not a table")))?;
+    if rest.is_empty() {
+        table.insert((*field).to_string(), new_value);
+    } else {
+        let child = table
+            .entry((*field).to_string())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        set_path(child, rest, new_value)?;
+    }
+    Ok(())
 }
 
 /// Global configuration.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct GlobalConfig {
     /// Log verbosity.
-    #[serde(deserialize_with = "deserialize_level_from_string", skip_serializing)]
+    #[serde(skip_serializing)]
     pub log_verbosity: Level,
     /// Whether if a startup notification should be shown.
     pub startup_notification: bool,
     /// Geometry of the notification window.
-    #[serde(deserialize_with = "deserialize_geometry_from_string")]
     pub geometry: Geometry,
+    /// Monitor the notification window is placed on.
+    ///
+    /// One of `"pointer"` (the monitor currently under the mouse cursor), `"primary"`, or a
+    /// RANDR output name such as `"DP-1"`. Defaults to `"pointer"` if left empty.
+    pub monitor: String,
     /// Whether if the window will be resized to wrap the content.
     pub wrap_content: bool,
+    /// Whether to set EWMH/ICCCM window-manager hints (`_NET_WM_WINDOW_TYPE_NOTIFICATION`,
+    /// `_NET_WM_STATE_ABOVE`, `WM_CLASS`, `_NET_WM_NAME`) on the notification window.
+    ///
+    /// Disable this to fall back to a plain `override_redirect` window, e.g. if a window
+    /// manager mishandles the hints.
+    pub window_type_hint: bool,
     /// Text font.
     pub font: String,
     /// Template for the notification message.
     pub template: String,
+    /// External menu program used to browse the notification history.
+    pub menu: MenuConfig,
+    /// D-Bus well-known name to request, e.g. `org.freedesktop.Notifications`.
+    ///
+    /// Overriding this (together with [`object_path`](Self::object_path)) lets a second
+    /// `runst` instance run side-by-side with the user's real notification daemon, e.g. for
+    /// integration testing.
+    pub bus_name: String,
+    /// D-Bus object path to serve the notifications interface at, e.g.
+    /// `/org/freedesktop/Notifications`.
+    pub object_path: String,
+    /// Configuration for the optional notification icon/image.
+    pub icon: IconConfig,
+    /// Configuration for stacking multiple concurrently-shown notification windows.
+    pub stack: StackConfig,
+    /// Maps a notification action key (as invoked via [`crate::notification::Action::InvokeAction`])
+    /// to a shell command template, rendered with the same `Tera` context as
+    /// [`UrgencyConfig::run_commands`] and run in addition to emitting `ActionInvoked`.
+    pub action_commands: HashMap<String, String>,
+    /// Configuration for coalescing repeated notifications into a single stacked entry.
+    pub coalesce: CoalesceConfig,
+    /// Configuration for persisting notification history to disk.
+    pub history: HistoryConfig,
+    /// Configuration for the queued delivery mode, used to stay responsive under notification
+    /// storms.
+    pub delivery: DeliveryConfig,
 }
 
-/// Custom deserializer implementation for converting `String` to [`Level`]
-fn deserialize_level_from_string<'de, D>(deserializer: D) -> StdResult<Level, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let value: String = Deserialize::deserialize(deserializer)?;
-    Level::from_str(&value).map_err(SerdeError::custom)
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        Self {
+            log_verbosity: Level::INFO,
+            startup_notification: true,
+            geometry: Geometry::default(),
+            monitor: String::from("pointer"),
+            wrap_content: true,
+            window_type_hint: true,
+            font: String::from("monospace 10"),
+            template: String::from("<b>{{ app_name }}</b>\n{{ summary }}\n{{ body }}"),
+            menu: MenuConfig::default(),
+            bus_name: String::from("org.freedesktop.Notifications"),
+            object_path: String::from("/org/freedesktop/Notifications"),
+            icon: IconConfig::default(),
+            stack: StackConfig::default(),
+            action_commands: HashMap::new(),
+            coalesce: CoalesceConfig::default(),
+            history: HistoryConfig::default(),
+            delivery: DeliveryConfig::default(),
+        }
+    }
 }
 
-/// Custom deserializer implementation for converting `String` to [`Geometry`]
-fn deserialize_geometry_from_string<'de, D>(deserializer: D) -> StdResult<Geometry, D::Error>
+/// Deserializes a [`GlobalConfig`] field-by-field: a field that is missing or fails to parse
+/// falls back to its [`Default`] value (with a `tracing::warn!` naming the offending key for
+/// the latter case), rather than failing the whole configuration.
+impl<'de> Deserialize<'de> for GlobalConfig {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let table = toml::Value::deserialize(deserializer)?
+            .as_table()
+            .cloned()
+            .unwrap_or_default();
+        let default = Self::default();
+        Ok(Self {
+            log_verbosity: match table.get("log_verbosity") {
+                Some(v) => v
+                    .as_str()
+                    .ok_or_else(|| String::from("expected a string"))
+                    .and_then(|v| Level::from_str(v).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("invalid value for `log_verbosity`, using default: {e}");
+                        default.log_verbosity
+                    }),
+                None => default.log_verbosity,
+            },
+            startup_notification: field_or_default(
+                &table,
+                "startup_notification",
+                default.startup_notification,
+            ),
+            geometry: match table.get("geometry") {
+                Some(v) => v
+                    .as_str()
+                    .ok_or_else(|| String::from("expected a string"))
+                    .and_then(|v| Geometry::from_str(v).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("invalid value for `geometry`, using default: {e}");
+                        default.geometry
+                    }),
+                None => default.geometry,
+            },
+            monitor: field_or_default(&table, "monitor", default.monitor),
+            wrap_content: field_or_default(&table, "wrap_content", default.wrap_content),
+            window_type_hint: field_or_default(
+                &table,
+                "window_type_hint",
+                default.window_type_hint,
+            ),
+            font: field_or_default(&table, "font", default.font),
+            template: field_or_default(&table, "template", default.template),
+            menu: field_or_default(&table, "menu", default.menu),
+            bus_name: field_or_default(&table, "bus_name", default.bus_name),
+            object_path: field_or_default(&table, "object_path", default.object_path),
+            icon: field_or_default(&table, "icon", default.icon),
+            stack: field_or_default(&table, "stack", default.stack),
+            action_commands: field_or_default(
+                &table,
+                "action_commands",
+                default.action_commands,
+            ),
+            coalesce: field_or_default(&table, "coalesce", default.coalesce),
+            history: field_or_default(&table, "history", default.history),
+            delivery: field_or_default(&table, "delivery", default.delivery),
+        })
+    }
+}
+
+/// Looks up `key` in `table` and deserializes it as `T`, falling back to `default` (with a
+/// `tracing::warn!` naming `key`) if the value is present but fails to parse. A missing key
+/// falls back to `default` silently, so partial user config files only need to specify the
+/// fields they want to override.
+fn field_or_default<T>(table: &toml::value::Table, key: &str, default: T) -> T
 where
-    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
 {
-    let value: String = Deserialize::deserialize(deserializer)?;
-    Geometry::from_str(&value).map_err(SerdeError::custom)
+    match table.get(key) {
+        Some(value) => match value.clone().try_into::<T>() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                tracing::warn!("invalid value for `{key}`, using default: {e}");
+                default
+            }
+        },
+        None => default,
+    }
 }
 
-/// Window geometry.
+/// Window geometry, following the `WIDTHxHEIGHT{+-}X{+-}Y` convention of the X geometry
+/// specification (e.g. `300x100+10+10` or `300x100-10-10`).
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Geometry {
     /// Width of the window.
     pub width: u32,
     /// Height of the window.
     pub height: u32,
-    /// X coordinate.
+    /// X offset, relative to the chosen monitor's left edge (or its right edge, if
+    /// [`x_negative`](Self::x_negative) is set).
     pub x: u32,
-    /// Y coordinate.
+    /// Y offset, relative to the chosen monitor's top edge (or its bottom edge, if
+    /// [`y_negative`](Self::y_negative) is set).
     pub y: u32,
+    /// Whether `x` is anchored to the monitor's right edge rather than its left edge.
+    #[serde(skip)]
+    pub x_negative: bool,
+    /// Whether `y` is anchored to the monitor's bottom edge rather than its top edge.
+    #[serde(skip)]
+    pub y_negative: bool,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self::from_str("300x100+10+10").expect("default geometry string is well-formed")
+    }
 }
 
 impl FromStr for Geometry {
     type Err = Error;
     fn from_str(s: &str) -> StdResult<Self, Self::Err> {
-        let (width, height, x, y) =
-            scanf!(s, "{u32}x{u32}+{u32}+{u32}").map_err(|e| Error::Scanf(e.to_string()))?;
+        let (width, height, x_sign, x, y_sign, y) =
+            scanf!(s, "{u32}x{u32}{char}{u32}{char}{u32}").map_err(|e| Error::Scanf(e.to_string()))?;
         Ok(Self {
             width,
             height,
             x,
             y,
+            x_negative: x_sign == '-',
+            y_negative: y_sign == '-',
         })
     }
 }
 
-/// Urgency configuration.
+/// Configuration for the external menu program used to browse notification history.
+///
+/// See [`rofi`](crate::rofi).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MenuConfig {
+    /// Command to run, e.g. `"rofi"`, `"dmenu"`, or `"fuzzel"`.
+    pub command: String,
+    /// Arguments passed to the menu command, e.g. `["-dmenu"]`.
+    pub args: Vec<String>,
+}
+
+impl Default for MenuConfig {
+    fn default() -> Self {
+        Self {
+            command: String::from("rofi"),
+            args: vec![String::from("-dmenu")],
+        }
+    }
+}
+
+/// Configuration for the optional notification icon/image.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IconConfig {
+    /// Whether icons should be rendered at all.
+    pub enabled: bool,
+    /// Size (in pixels) the icon is scaled to, along both axes.
+    pub size: u32,
+    /// Space (in pixels) between the icon and the templated text.
+    pub padding: u32,
+}
+
+impl Default for IconConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 32,
+            padding: 8,
+        }
+    }
+}
+
+/// Configuration for stacking multiple concurrently-shown notification windows.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StackConfig {
+    /// Direction additional notification windows are stacked in, relative to the first one.
+    pub direction: StackDirection,
+    /// Vertical gap (in pixels) between stacked windows.
+    pub gap: u32,
+    /// Maximum number of notification windows shown at once; the oldest is evicted once
+    /// this is exceeded.
+    pub max_visible: usize,
+}
+
+impl Default for StackConfig {
+    fn default() -> Self {
+        Self {
+            direction: StackDirection::default(),
+            gap: 10,
+            max_visible: 5,
+        }
+    }
+}
+
+/// Direction additional notification windows are stacked in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackDirection {
+    /// Stack downwards, i.e. each new window is placed below the previous one.
+    Down,
+    /// Stack upwards, i.e. each new window is placed above the previous one.
+    Up,
+}
+
+impl Default for StackDirection {
+    fn default() -> Self {
+        Self::Down
+    }
+}
+
+/// Configuration for coalescing repeated notifications from the same source into a single
+/// stacked entry, instead of flooding the window stack.
+///
+/// See [`Manager::add`](crate::notification::Manager::add).
 #[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CoalesceConfig {
+    /// Whether coalescing is enabled at all.
+    pub enabled: bool,
+    /// Window (in seconds) within which a notification matching a recent, still-unread one is
+    /// folded into it instead of creating a new entry.
+    pub window: u64,
+    /// Notification filters selecting which notifications are coalesced by [`app_name`] alone,
+    /// ignoring `summary`; an unmatched notification is still coalesced, but only with a
+    /// previous one that shares both `app_name` and `summary`.
+    ///
+    /// [`app_name`]: crate::notification::Notification::app_name
+    pub rules: Vec<NotificationFilter>,
+    /// Whether [`Urgency::Critical`] notifications are exempt from coalescing.
+    pub disable_for_critical: bool,
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: 10,
+            rules: Vec::new(),
+            disable_for_critical: true,
+        }
+    }
+}
+
+/// Configuration for persisting notification history to disk, so it survives daemon restarts.
+///
+/// See [`crate::history::HistoryStore`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryConfig {
+    /// Whether history is persisted to disk at all.
+    pub enabled: bool,
+    /// Maximum number of rows retained; the oldest are pruned once this is exceeded. `0` means
+    /// unlimited.
+    pub max_entries: usize,
+    /// Maximum age (in seconds) a row is retained for before being pruned. `0` means unlimited.
+    pub max_age: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 500,
+            max_age: 0,
+        }
+    }
+}
+
+/// How incoming notifications are delivered to the window stack.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    /// Notifications are shown as soon as they arrive (the default).
+    Immediate,
+    /// Notifications are queued and rendered in debounced batches, with per-`app_name` rate
+    /// limiting. See [`DeliveryConfig`].
+    Queued,
+}
+
+impl Default for DeliveryMode {
+    fn default() -> Self {
+        Self::Immediate
+    }
+}
+
+/// Configuration for rate-limiting notifications per `app_name` via a token bucket, used in
+/// [`DeliveryMode::Queued`] mode.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// Maximum number of tokens (i.e. notifications) a single `app_name` can burst before being
+    /// throttled.
+    pub burst: u32,
+    /// Tokens refilled per second.
+    pub refill_per_sec: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            burst: 10,
+            refill_per_sec: 2,
+        }
+    }
+}
+
+/// Configuration for [`DeliveryMode::Queued`] delivery, used to stay responsive under
+/// notification storms.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeliveryConfig {
+    /// Which delivery mode to use.
+    pub mode: DeliveryMode,
+    /// Maximum number of queued notifications awaiting a batch render. Once full, each incoming
+    /// notification evicts the oldest queued one, and evicted notifications are folded into a
+    /// single "+K more" summary entry.
+    pub queue_capacity: usize,
+    /// How long (in milliseconds) to wait for more notifications to arrive before rendering a
+    /// batch.
+    pub batch_window_ms: u64,
+    /// Per-`app_name` rate limiting.
+    pub rate_limit: RateLimitConfig,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            mode: DeliveryMode::default(),
+            queue_capacity: 1024,
+            batch_window_ms: 100,
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+/// Urgency configuration.
+#[derive(Clone, Debug, Serialize)]
 pub struct UrgencyConfig {
     /// Background color.
-    #[serde(
-        deserialize_with = "deserialize_rgb_from_string",
-        serialize_with = "serialize_rgb_to_string"
-    )]
+    #[serde(serialize_with = "serialize_rgb_to_string")]
     pub background: Rgb,
     /// Foreground color.
-    #[serde(
-        deserialize_with = "deserialize_rgb_from_string",
-        serialize_with = "serialize_rgb_to_string"
-    )]
+    #[serde(serialize_with = "serialize_rgb_to_string")]
     pub foreground: Rgb,
     /// Timeout value.
     pub timeout: u32,
@@ -169,13 +622,61 @@ pub struct UrgencyConfig {
     pub custom_commands: Option<Vec<CustomCommand>>,
 }
 
-/// Custom deserializer implementation for converting `String` to [`Rgb`]
-fn deserialize_rgb_from_string<'de, D>(deserializer: D) -> StdResult<Rgb, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let value: String = Deserialize::deserialize(deserializer)?;
-    Rgb::from_hex_str(&value).map_err(SerdeError::custom)
+impl Default for UrgencyConfig {
+    fn default() -> Self {
+        Self {
+            background: Rgb::from_hex_str("#1e1e2e").expect("default color is well-formed"),
+            foreground: Rgb::from_hex_str("#cdd6f4").expect("default color is well-formed"),
+            timeout: 5000,
+            auto_clear: None,
+            text: None,
+            custom_commands: None,
+        }
+    }
+}
+
+/// Deserializes a [`UrgencyConfig`] field-by-field: a field that is missing or fails to parse
+/// falls back to its [`Default`] value (with a `tracing::warn!` naming the offending key for
+/// the latter case), rather than failing the whole configuration.
+impl<'de> Deserialize<'de> for UrgencyConfig {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let table = toml::Value::deserialize(deserializer)?
+            .as_table()
+            .cloned()
+            .unwrap_or_default();
+        let default = Self::default();
+        Ok(Self {
+            background: match table.get("background") {
+                Some(v) => v
+                    .as_str()
+                    .ok_or_else(|| String::from("expected a string"))
+                    .and_then(|v| Rgb::from_hex_str(v).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("invalid value for `background`, using default: {e}");
+                        default.background
+                    }),
+                None => default.background,
+            },
+            foreground: match table.get("foreground") {
+                Some(v) => v
+                    .as_str()
+                    .ok_or_else(|| String::from("expected a string"))
+                    .and_then(|v| Rgb::from_hex_str(v).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("invalid value for `foreground`, using default: {e}");
+                        default.foreground
+                    }),
+                None => default.foreground,
+            },
+            timeout: field_or_default(&table, "timeout", default.timeout),
+            auto_clear: field_or_default(&table, "auto_clear", default.auto_clear),
+            text: field_or_default(&table, "text", default.text),
+            custom_commands: field_or_default(&table, "custom_commands", default.custom_commands),
+        })
+    }
 }
 
 /// Custom serializer implementation for converting [`Rgb`] to `String`
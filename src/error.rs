@@ -1,13 +1,14 @@
 #![allow(missing_docs)]
 
 use thiserror::Error as ThisError;
-use std::sync::mpsc::SendError;
 use crate::notification::Action;
 
 #[derive(Debug, ThisError)]
 pub enum Error {
     #[error("Channel send error: `{0}`")]
-    Send(#[from] SendError<Action>),
+    Send(#[from] calloop::channel::SendError<Action>),
+    #[error("Event loop error: `{0}`")]
+    EventLoop(String),
     #[error("IO error: `{0}`")]
     Io(#[from] std::io::Error),
     #[error("zbus error: `{0}`")]
@@ -20,6 +21,8 @@ pub enum Error {
     X11Connection(#[from] x11rb::errors::ConnectionError),
     #[error("X11 ID error: `{0}`")]
     X11Id(#[from] x11rb::errors::ReplyOrIdError),
+    #[error("X11 reply error: `{0}`")]
+    X11Reply(#[from] x11rb::errors::ReplyError),
     #[error("X11 error: `{0}`")]
     X11Other(String),
     #[error("Cairo error: `{0}`")]
@@ -28,6 +31,10 @@ pub enum Error {
     Receiver(#[from] std::sync::mpsc::RecvError),
     #[error("TOML parsing error: `{0}`")]
     Toml(#[from] toml::de::Error),
+    #[error("TOML serialization error: `{0}`")]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error("JSON error: `{0}`")]
+    Json(#[from] serde_json::Error),
     #[error("Scan error: `{0}`")]
     Scanf(String),
     #[error("Integer conversion error: `{0}`")]
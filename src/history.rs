@@ -0,0 +1,178 @@
+use crate::config::HistoryConfig;
+use crate::error::Result;
+use crate::notification::{Notification, Urgency};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the append-only notification history log, stored under the XDG data directory.
+const HISTORY_FILE: &str = concat!(env!("CARGO_PKG_NAME"), "-history.jsonl");
+
+/// The subset of a [`Notification`]'s fields that are persisted to disk.
+///
+/// Transient fields (`actions`, `icon`, `expire_timeout`) are deliberately left out, since
+/// they're only meaningful for a notification that's still being shown. `stack_count` is kept,
+/// since a notification re-shown from history (e.g. via [`crate::notification::Action::Reshow`])
+/// should still render its coalesced count rather than falling back to 0.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// The notification ID.
+    pub id: u32,
+    /// Name of the application that sent the notification.
+    pub app_name: String,
+    /// Summary text.
+    pub summary: String,
+    /// Body.
+    pub body: String,
+    /// Urgency.
+    pub urgency: Urgency,
+    /// Whether the notification has been read.
+    pub is_read: bool,
+    /// Timestamp the notification was created (or last coalesced into) at.
+    pub timestamp: u64,
+    /// Monotonically increasing sequence number the notification was assigned when first added.
+    ///
+    /// See [`Notification::seq`].
+    pub seq: u64,
+    /// Number of notifications coalesced into this one. See [`Notification::stack_count`].
+    ///
+    /// Defaults to 1 (rather than derived `Default`'s 0) for entries logged before this field
+    /// existed, matching the starting count of a notification received over D-Bus.
+    #[serde(default = "default_stack_count")]
+    pub stack_count: usize,
+}
+
+/// Default for [`HistoryEntry::stack_count`], for log entries written before the field existed.
+fn default_stack_count() -> usize {
+    1
+}
+
+impl From<&Notification> for HistoryEntry {
+    fn from(notification: &Notification) -> Self {
+        Self {
+            id: notification.id,
+            app_name: notification.app_name.clone(),
+            summary: notification.summary.clone(),
+            body: notification.body.clone(),
+            urgency: notification.urgency.clone(),
+            is_read: notification.is_read,
+            timestamp: notification.timestamp,
+            seq: notification.seq,
+            stack_count: notification.stack_count,
+        }
+    }
+}
+
+impl From<HistoryEntry> for Notification {
+    fn from(entry: HistoryEntry) -> Self {
+        Self {
+            id: entry.id,
+            app_name: entry.app_name,
+            summary: entry.summary,
+            body: entry.body,
+            urgency: entry.urgency,
+            is_read: entry.is_read,
+            timestamp: entry.timestamp,
+            seq: entry.seq,
+            stack_count: entry.stack_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// Append-only JSON-lines log of every notification's state, persisted across restarts.
+///
+/// The [`Manager`](crate::notification::Manager) mutations `add`, `mark_as_read`,
+/// `mark_unread`, `mark_all_as_read` and `mark_last_as_read` append a fresh snapshot of the
+/// affected entry (`mark_next_as_unread` is not written through); [`HistoryStore::load`]
+/// replays the log, keeping only the latest snapshot per ID, to rehydrate the manager on
+/// startup.
+#[derive(Debug)]
+pub struct HistoryStore {
+    path: PathBuf,
+    max_entries: usize,
+    max_age: u64,
+}
+
+impl HistoryStore {
+    /// Resolves the history log path under the XDG data directory, or returns `None` if history
+    /// is disabled in configuration or the data directory can't be determined.
+    pub fn init(config: &HistoryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let path = dirs::data_dir()?.join(env!("CARGO_PKG_NAME")).join(HISTORY_FILE);
+        Some(Self {
+            path,
+            max_entries: config.max_entries,
+            max_age: config.max_age,
+        })
+    }
+
+    /// Rehydrates the most recent state of every notification recorded in the log, applying the
+    /// configured retention policy and compacting the log to match as a side effect.
+    pub fn load(&self) -> Result<Vec<Notification>> {
+        let Ok(file) = File::open(&self.path) else {
+            return Ok(Vec::new());
+        };
+        let mut by_id = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) {
+                by_id.insert(entry.id, entry);
+            }
+        }
+        let mut entries: Vec<HistoryEntry> = by_id.into_values().collect();
+        entries.sort_by_key(|entry| entry.timestamp);
+        self.prune(&mut entries);
+        self.rewrite(&entries)?;
+        Ok(entries.into_iter().map(Notification::from).collect())
+    }
+
+    /// Appends a snapshot of `notification`'s current state to the log.
+    pub fn write_through(&self, notification: &Notification) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&HistoryEntry::from(notification))?)?;
+        Ok(())
+    }
+
+    /// Drops entries older than `max_age` (if non-zero) and, beyond that, the oldest entries in
+    /// excess of `max_entries` (if non-zero).
+    fn prune(&self, entries: &mut Vec<HistoryEntry>) {
+        if self.max_age > 0 {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            entries.retain(|entry| now.saturating_sub(entry.timestamp) <= self.max_age);
+        }
+        if self.max_entries > 0 && entries.len() > self.max_entries {
+            let excess = entries.len() - self.max_entries;
+            entries.drain(0..excess);
+        }
+    }
+
+    /// Compacts the log down to exactly `entries`, one line per entry.
+    fn rewrite(&self, entries: &[HistoryEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&self.path)?;
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        Ok(())
+    }
+}
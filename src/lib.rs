@@ -17,21 +17,88 @@ pub mod config;
 /// Notification manager.
 pub mod notification;
 
-use crate::config::Config;
+/// Persistent notification history.
+pub mod history;
+
+/// Notification history browser.
+pub mod rofi;
+
+/// Unix-socket control subsystem.
+pub mod socket;
+
+/// Client for the `runst msg` control subcommand.
+pub mod msg;
+
+/// Per-`app_name` token-bucket rate limiting.
+pub mod ratelimit;
+
+use crate::config::{Config, DeliveryMode, SharedConfig, StackDirection};
 use crate::error::Result;
-use crate::notification::Action;
-use crate::x11::X11;
+use crate::notification::{Action, CloseReason};
+use crate::ratelimit::RateLimiter;
+use crate::socket::Socket;
+use crate::x11::{X11Window, X11};
+use calloop::channel::{channel, Event as ChannelEvent, Sender as ActionSender};
+use calloop::generic::{Generic, Interest, Mode};
+use calloop::timer::{TimeoutAction, Timer};
+use calloop::{EventLoop, LoopHandle, RegistrationToken};
 use estimated_read_time::Options;
-use notification::{Manager, Notification, Urgency};
-use std::sync::mpsc;
-use std::sync::Arc;
+use notification::{AddOutcome, Manager, Notification, Urgency};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::os::fd::{AsFd, BorrowedFd, RawFd};
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, RwLock};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tera::Tera;
 use tracing_subscriber::EnvFilter;
+use x11rb::protocol::Event;
+
+/// Adapts a bare [`RawFd`] into something `calloop` can register as an event source.
+struct BorrowedRawFd(RawFd);
+
+impl AsFd for BorrowedRawFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the wrapped descriptor outlives the event source it's registered as, since
+        // both the X11 connection and the control socket live for the lifetime of `run()`.
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// State shared by all the event sources driven by the `calloop` event loop.
+struct State {
+    config: SharedConfig,
+    x11: X11,
+    /// Currently-shown notification windows, one per unread notification, in the order they
+    /// were created (oldest first).
+    windows: Vec<X11Window>,
+    notifications: Manager,
+    action_sender: ActionSender<Action>,
+    dbus_handle: Option<zbus_notify::DbusHandle>,
+    /// Pending notification expiries, as a min-heap keyed on the expiration [`Instant`].
+    expiries: Rc<RefCell<BinaryHeap<Reverse<(Instant, u32)>>>>,
+    /// Registration token and deadline of the currently-armed expiry timer source, if any, so
+    /// [`reschedule_timer`] doesn't stack a redundant duplicate source under a burst of
+    /// notifications whose deadlines are all later than the one already armed.
+    armed_timer: Rc<RefCell<Option<(RegistrationToken, Instant)>>>,
+    loop_handle: LoopHandle<'static, State>,
+    /// Notifications awaiting a batched render pass, used in [`DeliveryMode::Queued`] mode.
+    pending_queue: Rc<RefCell<VecDeque<Notification>>>,
+    /// Number of notifications dropped (because `pending_queue` was full or their sender was
+    /// rate-limited) since the last batch flush, surfaced as a "+K more" entry in that flush.
+    queue_dropped: Rc<RefCell<usize>>,
+    /// Whether a batch-flush timer is currently scheduled.
+    batch_scheduled: Rc<RefCell<bool>>,
+    /// Per-`app_name` token-bucket rate limiter, used in [`DeliveryMode::Queued`] mode.
+    rate_limiter: Rc<RefCell<RateLimiter>>,
+}
 
 /// Runs `runst`.
 pub fn run() -> Result<()> {
-    let config = Arc::new(Config::parse()?);
+    let config = Config::parse()?;
 
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -43,82 +110,128 @@ pub fn run() -> Result<()> {
     tracing::trace!("{:#?}", config);
     tracing::info!("starting runst with zbus");
 
-    let mut x11 = X11::init(None)?;
-    let window = x11.create_window(&config.global)?;
-
-    let x11 = Arc::new(x11);
-    let window = Arc::new(window);
-    let notifications = Manager::init();
+    let x11 = X11::init(None)?;
+    let history_store = history::HistoryStore::init(&config.global.history);
+    let notifications = Manager::init(history_store)?;
 
-    let (sender, receiver) = mpsc::channel();
+    let mut event_loop: EventLoop<'static, State> =
+        EventLoop::try_new().map_err(|e| error::Error::EventLoop(e.to_string()))?;
+    let loop_handle = event_loop.handle();
 
-    // Spawn X11 event handler thread
-    let x11_cloned = Arc::clone(&x11);
-    let window_cloned = Arc::clone(&window);
-    let config_cloned = Arc::clone(&config);
-    let notifications_cloned = notifications.clone();
-    let sender_cloned = sender.clone();
+    let (action_sender, action_channel) = channel::<Action>();
 
-    thread::spawn(move || {
-        if let Err(e) = x11_cloned.handle_events(
-            window_cloned,
-            notifications_cloned,
-            config_cloned,
-            move |notification| {
-                tracing::debug!("user input detected");
-                sender_cloned
-                    .send(Action::Close(Some(notification.id)))
-                    .expect("failed to send close action");
+    // Register the X11 connection: drain its events whenever the socket is readable, instead
+    // of blocking a dedicated thread on `wait_for_event`.
+    let x11_fd = BorrowedRawFd(x11.as_raw_fd());
+    loop_handle
+        .insert_source(
+            Generic::new(x11_fd, Interest::READ, Mode::Level),
+            |_, _, state: &mut State| {
+                handle_x11_events(state);
+                Ok(calloop::PostAction::Continue)
             },
-        ) {
-            eprintln!("Failed to handle X11 events: {e}")
+        )
+        .map_err(|e| error::Error::EventLoop(e.to_string()))?;
+
+    // Register the Unix-socket control subsystem.
+    match Socket::init() {
+        Ok(socket) => {
+            let socket_fd = BorrowedRawFd(socket.as_raw_fd());
+            loop_handle
+                .insert_source(
+                    Generic::new(socket_fd, Interest::READ, Mode::Level),
+                    move |_, _, state: &mut State| {
+                        if let Err(e) = socket.accept_pending(&state.action_sender) {
+                            tracing::warn!("failed to accept control socket connection: {}", e);
+                        }
+                        Ok(calloop::PostAction::Continue)
+                    },
+                )
+                .map_err(|e| error::Error::EventLoop(e.to_string()))?;
         }
-    });
+        Err(e) => eprintln!("Failed to bind control socket: {e}"),
+    }
+
+    // Register the Action channel fed by the socket source above, the history browser thread
+    // and the zbus D-Bus server thread (X11 events are dispatched directly).
+    loop_handle
+        .insert_source(action_channel, |event, _, state: &mut State| {
+            if let ChannelEvent::Msg(action) = event {
+                dispatch(state, action);
+            }
+        })
+        .map_err(|e| error::Error::EventLoop(e.to_string()))?;
 
     // Spawn zbus D-Bus server thread
-    let sender_for_dbus = sender.clone();
+    let sender_for_dbus = action_sender.clone();
+    let notifications_for_dbus = notifications.clone();
+    // Seed the D-Bus handler's `next_id` counter above any ID rehydrated from history, so a
+    // freshly assigned ID can't collide with a stale entry loaded on `Manager::init`.
+    let start_id = notifications_for_dbus.max_id();
+    let (dbus_handle_sender, dbus_handle_receiver) = mpsc::channel();
+    let bus_name = config.global.bus_name.clone();
+    let object_path = config.global.object_path.clone();
     thread::spawn(move || {
-        tracing::debug!("starting zbus D-Bus server thread");
-        
+        tracing::debug!("starting zbus D-Bus server thread on `{}`", bus_name);
+
         let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
         rt.block_on(async {
-            let notifications = zbus_notify::Notifications::new(sender_for_dbus.clone());
-            let control = zbus_notify::NotificationControl::new(sender_for_dbus);
+            let notifications = zbus_notify::Notifications::new(sender_for_dbus.clone(), start_id);
+            let control =
+                zbus_notify::NotificationControl::new(sender_for_dbus, notifications_for_dbus);
+            let control_path = format!("{}/ctl", object_path);
 
             match zbus::connection::Builder::session() {
                 Ok(mut builder) => {
                     // Request the well-known name
-                    builder = match builder.name("org.freedesktop.Notifications") {
+                    builder = match builder.name(bus_name.as_str()) {
                         Ok(b) => b,
                         Err(e) => {
                             eprintln!("Failed to request name: {}", e);
                             return;
                         }
                     };
-                    
+
                     // Build the connection
                     match builder.build().await {
                         Ok(connection) => {
                             // Serve the notifications interface
                             if let Err(e) = connection
                                 .object_server()
-                                .at("/org/freedesktop/Notifications", notifications)
+                                .at(object_path.as_str(), notifications)
                                 .await
                             {
                                 eprintln!("Failed to serve notifications interface: {}", e);
                                 return;
                             }
-                            
+
                             // Serve the control interface
                             if let Err(e) = connection
                                 .object_server()
-                                .at("/org/freedesktop/Notifications/ctl", control)
+                                .at(control_path.as_str(), control)
                                 .await
                             {
                                 eprintln!("Failed to serve control interface: {}", e);
                                 return;
                             }
-                            
+
+                            // Hand a signal-emitting handle back to the main loop so it can
+                            // drive `ActionInvoked` (and friends) from outside of an
+                            // interface method.
+                            match zbus::object_server::SignalEmitter::new(
+                                connection.clone(),
+                                object_path.as_str(),
+                            ) {
+                                Ok(emitter) => {
+                                    let handle = zbus_notify::DbusHandle::new(
+                                        tokio::runtime::Handle::current(),
+                                        emitter,
+                                    );
+                                    let _ = dbus_handle_sender.send(handle);
+                                }
+                                Err(e) => eprintln!("Failed to create signal emitter: {}", e),
+                            }
+
                             tracing::info!("zbus D-Bus server is running");
                             // Keep the connection alive
                             std::future::pending::<()>().await;
@@ -135,10 +248,38 @@ pub fn run() -> Result<()> {
         });
     });
 
-    // Small delay to let D-Bus server start
+    // Give the D-Bus server a moment to come up before picking up its signal emitter.
     thread::sleep(Duration::from_millis(100));
+    let dbus_handle = dbus_handle_receiver.recv_timeout(Duration::from_secs(2)).ok();
+    if dbus_handle.is_none() {
+        tracing::warn!("zbus signal emitter was not ready in time, actions will not be reported back to clients");
+    }
+
+    let rate_limiter = RateLimiter::new(config.global.delivery.rate_limit.clone());
 
-    if config.global.startup_notification {
+    let mut state = State {
+        config: Arc::new(RwLock::new(config)),
+        x11,
+        windows: Vec::new(),
+        notifications,
+        action_sender,
+        dbus_handle,
+        expiries: Rc::new(RefCell::new(BinaryHeap::new())),
+        armed_timer: Rc::new(RefCell::new(None)),
+        loop_handle,
+        pending_queue: Rc::new(RefCell::new(VecDeque::new())),
+        queue_dropped: Rc::new(RefCell::new(0)),
+        batch_scheduled: Rc::new(RefCell::new(false)),
+        rate_limiter: Rc::new(RefCell::new(rate_limiter)),
+    };
+
+    let startup_notification_enabled = state
+        .config
+        .read()
+        .expect("failed to read config")
+        .global
+        .startup_notification;
+    if startup_notification_enabled {
         let startup_notification = Notification {
             id: 0,
             app_name: env!("CARGO_PKG_NAME").to_string(),
@@ -151,73 +292,535 @@ pub fn run() -> Result<()> {
                 .duration_since(UNIX_EPOCH)
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
+            actions: Vec::new(),
+            icon: None,
+            stack_count: 1,
+            seq: 0,
         };
-        sender.send(Action::Show(startup_notification))?;
-    }
-
-    let x11_cloned = Arc::clone(&x11);
-    loop {
-        match receiver.recv()? {
-            Action::Show(notification) => {
-                tracing::debug!("received notification: {}", notification.id);
-                let timeout = notification.expire_timeout.unwrap_or_else(|| {
-                    let urgency_config = config.get_urgency_config(&notification.urgency);
-                    Duration::from_secs(if urgency_config.auto_clear.unwrap_or(false) {
-                        notification
-                            .render_message(&window.template, urgency_config.text, 0)
-                            .map(|v| estimated_read_time::text(&v, &Options::default()).seconds())
-                            .unwrap_or_default()
-                    } else {
-                        urgency_config.timeout.into()
-                    })
-                });
-                if !timeout.is_zero() {
-                    tracing::debug!("notification timeout: {}ms", timeout.as_millis());
-                    let sender_cloned = sender.clone();
-                    let notifications_cloned = notifications.clone();
-                    let notification_id = notification.id;
-                    thread::spawn(move || {
-                        thread::sleep(timeout);
-                        if notifications_cloned.is_unread(notification_id) {
-                            sender_cloned
-                                .send(Action::Close(Some(notification_id)))
-                                .expect("failed to send close action");
-                        }
-                    });
+        dispatch(&mut state, Action::Show(startup_notification));
+    }
+
+    event_loop
+        .run(None, &mut state, |_| {})
+        .map_err(|e| error::Error::EventLoop(e.to_string()))
+}
+
+/// Schedules `id`'s expiry after `timeout` on the shared min-heap timer, (re)starting the
+/// backing `calloop` timer source if this is the next-soonest deadline.
+fn schedule_expiry(state: &State, id: u32, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    state.expiries.borrow_mut().push(Reverse((deadline, id)));
+    reschedule_timer(state);
+}
+
+/// (Re)installs the single timer source for the next-earliest pending expiry, if any.
+///
+/// A no-op if a timer source is already armed for that deadline or an earlier one: it will pick
+/// up the new deadline (and any others due by then) when it fires and calls this again. This
+/// keeps a burst of notifications — all later than whatever's already armed — from stacking a
+/// redundant duplicate timer source per `Show`.
+fn reschedule_timer(state: &State) {
+    let next_deadline = state
+        .expiries
+        .borrow()
+        .peek()
+        .map(|Reverse((instant, _))| *instant);
+    let Some(deadline) = next_deadline else {
+        return;
+    };
+    if let Some((_, armed_deadline)) = *state.armed_timer.borrow() {
+        if armed_deadline <= deadline {
+            return;
+        }
+    }
+    if let Some((token, _)) = state.armed_timer.borrow_mut().take() {
+        state.loop_handle.remove(token);
+    }
+
+    let expiries = Rc::clone(&state.expiries);
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    let token = state
+        .loop_handle
+        .insert_source(Timer::from_duration(timeout), move |_, _, state: &mut State| {
+            *state.armed_timer.borrow_mut() = None;
+            let now = Instant::now();
+            let mut due = Vec::new();
+            {
+                let mut expiries = expiries.borrow_mut();
+                while matches!(expiries.peek(), Some(Reverse((instant, _))) if *instant <= now) {
+                    if let Some(Reverse((_, id))) = expiries.pop() {
+                        due.push(id);
+                    }
+                }
+            }
+            for id in due {
+                if state.notifications.is_unread(id) {
+                    dispatch(state, Action::Close(Some(id), CloseReason::Expired));
                 }
-                notifications.add(notification);
-                x11_cloned.hide_window(&window)?;
-                x11_cloned.show_window(&window)?;
-            }
-            Action::ShowLast => {
-                tracing::debug!("showing the last notification");
-                if notifications.count() == 0 {
-                    continue;
-                } else if notifications.mark_next_as_unread() {
-                    x11_cloned.hide_window(&window)?;
-                    x11_cloned.show_window(&window)?;
+            }
+            reschedule_timer(state);
+            TimeoutAction::Drop
+        });
+    if let Ok(token) = token {
+        *state.armed_timer.borrow_mut() = Some((token, deadline));
+    }
+}
+
+/// Shows `notification` immediately: coalesces it into an existing unread entry if applicable,
+/// otherwise creates and shows a new window for it.
+fn show_notification(state: &mut State, notification: Notification) -> Result<()> {
+    let config = state.config.read().expect("failed to read config");
+    let coalesce = &config.global.coalesce;
+    let is_critical = matches!(notification.urgency, Urgency::Critical);
+    let coalesce_window = (coalesce.enabled && !(coalesce.disable_for_critical && is_critical))
+        .then(|| Duration::from_secs(coalesce.window));
+    let rules = coalesce.rules.clone();
+    let added = notification.clone();
+    match state.notifications.add(notification, coalesce_window, &rules) {
+        AddOutcome::Added => {
+            let urgency_config = config.get_urgency_config(&added.urgency);
+            let window = state.x11.create_window(&config.global, added.id)?;
+            let timeout = added.expire_timeout.unwrap_or_else(|| {
+                Duration::from_secs(if urgency_config.auto_clear.unwrap_or(false) {
+                    added
+                        .render_message(&window.template, urgency_config.text, 0)
+                        .map(|v| estimated_read_time::text(&v, &Options::default()).seconds())
+                        .unwrap_or_default()
                 } else {
-                    x11_cloned.hide_window(&window)?;
+                    urgency_config.timeout.into()
+                })
+            });
+            drop(config);
+            if !timeout.is_zero() {
+                tracing::debug!("notification timeout: {}ms", timeout.as_millis());
+                schedule_expiry(state, added.id, timeout);
+            }
+            state.windows.push(window);
+            enforce_stack_limit(state)?;
+            reflow(state)?;
+            if let Some(window) = state.windows.last() {
+                state.x11.show_window(window)?;
+            }
+            if let Some(dbus_handle) = &state.dbus_handle {
+                if let Some(stored) = state.notifications.get(added.id) {
+                    if let Err(e) = dbus_handle.emit_notification_added(
+                        stored.seq,
+                        stored.summary,
+                        stored.body,
+                    ) {
+                        tracing::warn!("failed to emit NotificationAdded signal: {}", e);
+                    }
                 }
             }
-            Action::Close(id) => {
-                if let Some(id) = id {
-                    tracing::debug!("closing notification: {}", id);
-                    notifications.mark_as_read(id);
+        }
+        AddOutcome::Coalesced(id) => {
+            tracing::debug!("coalesced notification into existing entry: {}", id);
+            let (Some(notification), Some(window)) = (
+                state.notifications.get(id),
+                state.windows.iter().find(|w| w.notification_id == id),
+            ) else {
+                return Ok(());
+            };
+            let urgency_config = config.get_urgency_config(&notification.urgency);
+            let timeout = notification.expire_timeout.unwrap_or_else(|| {
+                Duration::from_secs(if urgency_config.auto_clear.unwrap_or(false) {
+                    notification
+                        .render_message(&window.template, urgency_config.text, 0)
+                        .map(|v| estimated_read_time::text(&v, &Options::default()).seconds())
+                        .unwrap_or_default()
                 } else {
-                    tracing::debug!("closing the last notification");
-                    notifications.mark_last_as_read();
+                    urgency_config.timeout.into()
+                })
+            });
+            let unread_count = state.notifications.get_unread_count();
+            state.x11.draw_window(window, notification, unread_count, &config)?;
+            drop(config);
+            if !timeout.is_zero() {
+                schedule_expiry(state, id, timeout);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Queues `notification` for a debounced batch render, applying the configured per-`app_name`
+/// rate limit and the queue's capacity (dropping the oldest entry, tallied for a "+K more"
+/// summary, once full).
+fn enqueue_notification(state: &mut State, notification: Notification) {
+    if !state.rate_limiter.borrow_mut().try_acquire(&notification.app_name) {
+        tracing::debug!("rate-limited notification from `{}`", notification.app_name);
+        *state.queue_dropped.borrow_mut() += 1;
+        return;
+    }
+    let capacity = state
+        .config
+        .read()
+        .expect("failed to read config")
+        .global
+        .delivery
+        .queue_capacity;
+    {
+        let mut queue = state.pending_queue.borrow_mut();
+        if queue.len() >= capacity {
+            queue.pop_front();
+            *state.queue_dropped.borrow_mut() += 1;
+        }
+        queue.push_back(notification);
+    }
+    schedule_batch_flush(state);
+}
+
+/// (Re)installs the single `calloop` timer source that, after the configured debounce window,
+/// drains `pending_queue` and renders it as a batch, unless one is already scheduled.
+fn schedule_batch_flush(state: &State) {
+    if *state.batch_scheduled.borrow() {
+        return;
+    }
+    *state.batch_scheduled.borrow_mut() = true;
+
+    let batch_window_ms = state
+        .config
+        .read()
+        .expect("failed to read config")
+        .global
+        .delivery
+        .batch_window_ms;
+    let pending_queue = Rc::clone(&state.pending_queue);
+    let queue_dropped = Rc::clone(&state.queue_dropped);
+    let batch_scheduled = Rc::clone(&state.batch_scheduled);
+    let _ = state.loop_handle.insert_source(
+        Timer::from_duration(Duration::from_millis(batch_window_ms)),
+        move |_, _, state: &mut State| {
+            *batch_scheduled.borrow_mut() = false;
+            let batch: Vec<Notification> = pending_queue.borrow_mut().drain(..).collect();
+            let dropped = std::mem::take(&mut *queue_dropped.borrow_mut());
+            tracing::debug!("flushing batch of {} queued notification(s)", batch.len());
+            for notification in batch {
+                if let Err(e) = show_notification(state, notification) {
+                    tracing::warn!("failed to show queued notification: {}", e);
+                }
+            }
+            if dropped > 0 {
+                let summary = Notification {
+                    id: 0,
+                    app_name: env!("CARGO_PKG_NAME").to_string(),
+                    summary: "Notification storm".to_string(),
+                    body: format!("+{} more notifications were dropped", dropped),
+                    urgency: Urgency::Low,
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default(),
+                    stack_count: dropped + 1,
+                    ..Default::default()
+                };
+                if let Err(e) = show_notification(state, summary) {
+                    tracing::warn!("failed to show dropped-notification summary: {}", e);
+                }
+            }
+            TimeoutAction::Drop
+        },
+    );
+}
+
+/// Handles a single [`Action`], logging (rather than propagating) any failure so that one bad
+/// action can't bring down the event loop.
+fn dispatch(state: &mut State, action: Action) {
+    if let Err(e) = try_dispatch(state, action) {
+        tracing::warn!("failed to handle action: {}", e);
+    }
+}
+
+/// Drains and interprets all pending X11 events, dispatching the actions (closing a
+/// notification, invoking one of its actions, redrawing on expose) they imply.
+fn handle_x11_events(state: &mut State) {
+    let events = match state.x11.poll_events() {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::warn!("failed to poll X11 events: {}", e);
+            return;
+        }
+    };
+    for event in events {
+        handle_window_interaction(state, event);
+    }
+}
+
+/// Interprets a single X11 event against the window stack, redrawing or dispatching an
+/// [`Action`] for the notification the affected window belongs to.
+fn handle_window_interaction(state: &mut State, event: Event) {
+    match event {
+        Event::Expose(expose) => {
+            let Some(window) = state.windows.iter().find(|w| w.id == expose.window) else {
+                return;
+            };
+            let Some(notification) = state.notifications.get(window.notification_id) else {
+                return;
+            };
+            let unread_count = state.notifications.get_unread_count();
+            let config = state.config.read().expect("failed to read config");
+            if let Err(e) = state.x11.draw_window(window, notification, unread_count, &config) {
+                tracing::warn!("failed to draw notification window: {}", e);
+            }
+        }
+        Event::ButtonPress(button_press) => {
+            let Some(id) = window_notification_id(state, button_press.event) else {
+                return;
+            };
+            let Some(notification) = state.notifications.get(id) else {
+                return;
+            };
+            state.notifications.mark_as_read(id);
+            let action = match notification.default_action() {
+                Some(key) => Action::InvokeAction(id, key.to_string()),
+                None => Action::Close(Some(id), CloseReason::Dismissed),
+            };
+            dispatch(state, action);
+        }
+        Event::KeyPress(key_press) => {
+            let Some(id) = window_notification_id(state, key_press.event) else {
+                return;
+            };
+            let Some(notification) = state.notifications.get(id) else {
+                return;
+            };
+            // The default XKB layout maps the number row "1".."9" to keycodes 10..18, which we
+            // use to pick one of the notification's actions.
+            let action_key = match key_press.detail {
+                10..=18 => notification.nth_action((key_press.detail - 10) as usize),
+                _ => None,
+            };
+            if let Some(action_key) = action_key {
+                state.notifications.mark_as_read(id);
+                dispatch(state, Action::InvokeAction(id, action_key.to_string()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the ID of the notification shown by the window with the given X11 window ID, if any.
+fn window_notification_id(state: &State, window_id: u32) -> Option<u32> {
+    state
+        .windows
+        .iter()
+        .find(|w| w.id == window_id)
+        .map(|w| w.notification_id)
+}
+
+/// Destroys the X11 window showing notification `id`, if one exists, and removes it from the
+/// stack.
+fn destroy_window_for(state: &mut State, id: u32) -> Result<()> {
+    if let Some(index) = state.windows.iter().position(|w| w.notification_id == id) {
+        let window = state.windows.remove(index);
+        state.x11.destroy_window(&window)?;
+    }
+    Ok(())
+}
+
+/// Evicts the oldest notification windows until at most `stack.max_visible` remain.
+fn enforce_stack_limit(state: &mut State) -> Result<()> {
+    let max_visible = state
+        .config
+        .read()
+        .expect("failed to read config")
+        .global
+        .stack
+        .max_visible;
+    while state.windows.len() > max_visible {
+        let window = state.windows.remove(0);
+        state.x11.destroy_window(&window)?;
+    }
+    Ok(())
+}
+
+/// Re-flows the notification stack, repositioning every window based on the actual,
+/// currently-drawn height ([`X11::window_height`]) of the windows before it plus the
+/// configured gap.
+fn reflow(state: &State) -> Result<()> {
+    let config = state.config.read().expect("failed to read config");
+    let (base_x, base_y) = state.x11.base_position(&config.global)?;
+    let windows: Vec<&X11Window> = match config.global.stack.direction {
+        StackDirection::Down => state.windows.iter().collect(),
+        StackDirection::Up => state.windows.iter().rev().collect(),
+    };
+    let mut offset: i32 = 0;
+    for window in windows {
+        let height = state
+            .x11
+            .window_height(window)
+            .unwrap_or(config.global.geometry.height as u16);
+        let y = match config.global.stack.direction {
+            StackDirection::Down => i32::from(base_y) + offset,
+            StackDirection::Up => {
+                i32::from(base_y) + config.global.geometry.height as i32
+                    - offset
+                    - i32::from(height)
+            }
+        };
+        state.x11.reposition_window(window, base_x, y as i16)?;
+        offset += i32::from(height) + config.global.stack.gap as i32;
+    }
+    Ok(())
+}
+
+/// Creates and shows windows for any currently-unread notification that doesn't have one yet.
+///
+/// Used by [`Action::ShowLast`] (a legacy action that predates the window-stack subsystem) and
+/// [`Action::Reshow`] (re-showing a notification picked from the history browser).
+fn show_missing_windows(state: &mut State) -> Result<()> {
+    let missing: Vec<Notification> = {
+        let windows = &state.windows;
+        state
+            .notifications
+            .all()
+            .into_iter()
+            .filter(|n| !n.is_read && !windows.iter().any(|w| w.notification_id == n.id))
+            .collect()
+    };
+    for notification in missing {
+        let config = state.config.read().expect("failed to read config");
+        let window = state.x11.create_window(&config.global, notification.id)?;
+        drop(config);
+        state.windows.push(window);
+    }
+    enforce_stack_limit(state)?;
+    reflow(state)?;
+    for window in &state.windows {
+        state.x11.show_window(window)?;
+    }
+    Ok(())
+}
+
+/// Runs the user-configured command for `action_key` (if any), mirroring
+/// [`UrgencyConfig::run_commands`](crate::config::UrgencyConfig::run_commands).
+fn run_action_command(state: &State, id: u32, action_key: &str) -> Result<()> {
+    let config = state.config.read().expect("failed to read config");
+    let Some(command) = config.global.action_commands.get(action_key) else {
+        return Ok(());
+    };
+    let Some(notification) = state.notifications.get(id) else {
+        return Ok(());
+    };
+    let urgency_config = config.get_urgency_config(&notification.urgency);
+    let urgency_text = urgency_config
+        .text
+        .clone()
+        .unwrap_or_else(|| notification.urgency.to_string());
+    let command = Tera::one_off(command, &notification.into_context(&urgency_text, 0)?, true)?;
+    Command::new("sh").args(["-c", &command]).spawn()?;
+    Ok(())
+}
+
+fn try_dispatch(state: &mut State, action: Action) -> Result<()> {
+    match action {
+        Action::Show(notification) => {
+            tracing::debug!("received notification: {}", notification.id);
+            let mode = state
+                .config
+                .read()
+                .expect("failed to read config")
+                .global
+                .delivery
+                .mode
+                .clone();
+            match mode {
+                DeliveryMode::Immediate => show_notification(state, notification)?,
+                DeliveryMode::Queued => enqueue_notification(state, notification),
+            }
+        }
+        Action::ShowLast => {
+            tracing::debug!("showing the last notification");
+            if state.notifications.count() == 0 {
+                return Ok(());
+            }
+            state.notifications.mark_next_as_unread();
+            show_missing_windows(state)?;
+        }
+        Action::Reshow(id) => {
+            tracing::debug!("re-showing notification from history: {}", id);
+            if state.notifications.mark_unread(id).is_some() {
+                show_missing_windows(state)?;
+            }
+        }
+        Action::BrowseHistory => {
+            tracing::debug!("opening notification history menu");
+            let action_sender = state.action_sender.clone();
+            let notifications = state.notifications.clone();
+            let menu_config = state
+                .config
+                .read()
+                .expect("failed to read config")
+                .global
+                .menu
+                .clone();
+            thread::spawn(move || match rofi::browse_history(&menu_config, &notifications) {
+                Ok(Some(action)) => {
+                    let _ = action_sender.send(action);
                 }
-                x11_cloned.hide_window(&window)?;
-                if notifications.get_unread_count() >= 1 {
-                    x11_cloned.show_window(&window)?;
+                Ok(None) => tracing::debug!("no notification selected in history menu"),
+                Err(e) => tracing::warn!("failed to browse notification history: {}", e),
+            });
+        }
+        Action::Close(id, reason) => {
+            let closed_id = if let Some(id) = id {
+                tracing::debug!("closing notification: {}", id);
+                state.notifications.mark_as_read(id);
+                Some(id)
+            } else {
+                tracing::debug!("closing the last notification");
+                state.notifications.mark_last_as_read()
+            };
+            if let Some(id) = closed_id {
+                destroy_window_for(state, id)?;
+                reflow(state)?;
+                if let Some(dbus_handle) = &state.dbus_handle {
+                    if let Err(e) = dbus_handle.emit_notification_closed(id, reason) {
+                        tracing::warn!("failed to emit NotificationClosed signal: {}", e);
+                    }
                 }
             }
-            Action::CloseAll => {
-                tracing::debug!("closing all notifications");
-                notifications.mark_all_as_read();
-                x11_cloned.hide_window(&window)?;
+        }
+        Action::CloseAll(reason) => {
+            tracing::debug!("closing all notifications");
+            let closed_ids = state.notifications.unread_ids();
+            state.notifications.mark_all_as_read();
+            for id in &closed_ids {
+                destroy_window_for(state, *id)?;
             }
+            if let Some(dbus_handle) = &state.dbus_handle {
+                for id in closed_ids {
+                    if let Err(e) = dbus_handle.emit_notification_closed(id, reason) {
+                        tracing::warn!("failed to emit NotificationClosed signal: {}", e);
+                    }
+                }
+            }
+        }
+        Action::InvokeAction(id, action_key) => {
+            tracing::debug!("invoking action `{}` for notification: {}", action_key, id);
+            if let Err(e) = run_action_command(state, id, &action_key) {
+                tracing::warn!("failed to run command for action `{}`: {}", action_key, e);
+            }
+            // Invoking an action (the default one on a click, or one picked via a number key)
+            // closes the notification's popup, same as `Close` — otherwise it would be stuck
+            // `is_read` with no window-stack/expiry-timer path left to ever clean it up.
+            state.notifications.mark_as_read(id);
+            destroy_window_for(state, id)?;
+            reflow(state)?;
+            if let Some(dbus_handle) = &state.dbus_handle {
+                if let Err(e) = dbus_handle.emit_action_invoked(id, action_key) {
+                    tracing::warn!("failed to emit ActionInvoked signal: {}", e);
+                }
+            }
+        }
+        Action::SetConfig(assignment) => {
+            tracing::debug!("applying config override: {}", assignment);
+            state
+                .config
+                .write()
+                .expect("failed to write config")
+                .apply_override(&assignment)?;
         }
     }
-}
\ No newline at end of file
+    Ok(())
+}
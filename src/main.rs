@@ -1,11 +1,7 @@
-mod zbusNotify;
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt().init();
-
-    let server = zbus_notify::Notifications::new();
-    server.run().await?;
-
-    Ok(())
+fn main() -> runst::error::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((cmd, rest)) if cmd == "msg" => runst::msg::run(rest),
+        _ => runst::run(),
+    }
 }
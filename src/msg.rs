@@ -0,0 +1,23 @@
+use crate::error::{Error, Result};
+use crate::socket::socket_path;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+
+/// Runs the `runst msg` subcommand, forwarding `args` as a single line to the daemon's
+/// control socket.
+///
+/// Mirrors the commands understood by [`crate::socket`]: `close [id]`, `close-all`,
+/// `history`, `invoke-action <id> <key>`, and `config <field.path>=<value>` to overlay a
+/// single TOML value onto the running configuration, e.g.
+/// `runst msg config urgency_normal.timeout=5000`.
+pub fn run(args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        return Err(Error::Config(String::from(
+            "usage: runst msg <command> [args...]",
+        )));
+    }
+    let command = args.join(" ");
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "{command}")?;
+    Ok(())
+}
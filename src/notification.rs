@@ -1,7 +1,10 @@
 use crate::error::{Error, Result};
+use crate::history::HistoryStore;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::error::Error as StdError;
+use std::hash::{Hash, Hasher};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tera::{Context as TeraContext, Tera};
@@ -10,7 +13,7 @@ use tera::{Context as TeraContext, Tera};
 pub const NOTIFICATION_MESSAGE_TEMPLATE: &str = "notification_message_template";
 
 /// Possible urgency levels for the notification.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Urgency {
     /// Low urgency.
     Low,
@@ -58,6 +61,59 @@ pub struct Notification {
     pub is_read: bool,
     /// Timestamp that the notification is created.
     pub timestamp: u64,
+    /// Actions that can be invoked for this notification.
+    ///
+    /// Each entry is a `(key, label)` pair, with the special `"default"` key
+    /// denoting the action that is invoked on a plain click.
+    pub actions: Vec<(String, String)>,
+    /// Icon/image to render alongside the notification, if any.
+    pub icon: Option<Icon>,
+    /// Number of notifications coalesced into this one so far, starting at 1.
+    ///
+    /// See [`Manager::add`].
+    pub stack_count: usize,
+    /// Monotonically increasing sequence number assigned when this entry was first added.
+    ///
+    /// Unlike `id`, which the spec allows a client to reuse via `replaces_id`, `seq` is never
+    /// reused and never decreases, so it can be used to reliably page through notifications
+    /// added since some previously observed point. See [`Manager::poll_since`].
+    pub seq: u64,
+}
+
+/// Icon/image attached to a notification, parsed from the `image-data`, `image-path`, or
+/// `app_icon` hints of the `Notify` call.
+///
+/// See [Icons and Images](https://specifications.freedesktop.org/notification-spec/latest/icons-and-images.html)
+#[derive(Clone, Debug)]
+pub enum Icon {
+    /// Raw pixel data, as delivered via the `image-data`/`icon_data` hint.
+    Data {
+        /// Width of the image, in pixels.
+        width: i32,
+        /// Height of the image, in pixels.
+        height: i32,
+        /// Number of bytes between the start of consecutive rows.
+        rowstride: i32,
+        /// Whether the image has an alpha channel.
+        has_alpha: bool,
+        /// Number of channels (e.g. 3 for RGB, 4 for RGBA).
+        channels: i32,
+        /// Raw, uncompressed pixel data.
+        data: Vec<u8>,
+    },
+    /// Path to an image file on disk, from the `image-path` hint or the `app_icon` argument.
+    Path(String),
+}
+
+impl Icon {
+    /// Returns the resolved file path, for [`Icon::Path`]; `None` for raw [`Icon::Data`], which
+    /// has no path to show.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Icon::Path(path) => Some(path),
+            Icon::Data { .. } => None,
+        }
+    }
 }
 
 impl Notification {
@@ -74,6 +130,8 @@ impl Notification {
             urgency_text,
             unread_count,
             timestamp: self.timestamp,
+            stack_count: self.stack_count,
+            icon_path: self.icon.as_ref().and_then(Icon::path),
         })?)
     }
 
@@ -99,6 +157,23 @@ impl Notification {
         }
     }
 
+    /// Returns the key of the default action (invoked on a plain click), if any.
+    pub fn default_action(&self) -> Option<&str> {
+        self.actions
+            .iter()
+            .find(|(key, _)| key == "default")
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Returns the key of the `n`th non-default action, if any.
+    pub fn nth_action(&self, n: usize) -> Option<&str> {
+        self.actions
+            .iter()
+            .filter(|(key, _)| key != "default")
+            .nth(n)
+            .map(|(key, _)| key.as_str())
+    }
+
     /// Returns true if the given filter matches the notification message.
     pub fn matches_filter(&self, filter: &NotificationFilter) -> bool {
         macro_rules! check_filter {
@@ -115,6 +190,19 @@ impl Notification {
         check_filter!(body);
         true
     }
+
+    /// Computes this notification's coalescing key: a hash of `app_name` and `summary`, or of
+    /// `app_name` alone if `rules` contains a filter matching this notification, so that e.g. a
+    /// chat app's rapid-fire messages (whose `summary` changes per message) still collapse into
+    /// a single stacked entry.
+    pub fn coalesce_key(&self, rules: &[NotificationFilter]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.app_name.hash(&mut hasher);
+        if !rules.iter().any(|filter| self.matches_filter(filter)) {
+            self.summary.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 }
 
 /// Notification message filter.
@@ -147,6 +235,41 @@ struct Context<'a> {
     pub unread_count: usize,
     /// Timestamp of the notification.
     pub timestamp: u64,
+    /// Number of notifications coalesced into this one so far, e.g. to render "(3) New
+    /// messages".
+    pub stack_count: usize,
+    /// Resolved path to the notification's icon/image, if any, so templates can render it
+    /// (e.g. via a custom renderer that shells out to `notify-send`-alikes).
+    pub icon_path: Option<&'a str>,
+}
+
+/// Reason for a notification being closed.
+///
+/// See the `NotificationClosed` signal in the [notification spec].
+///
+/// [notification spec]: https://specifications.freedesktop.org/notification-spec/latest/ar01s09.html
+#[derive(Clone, Copy, Debug)]
+pub enum CloseReason {
+    /// The notification expired.
+    Expired,
+    /// The notification was dismissed by the user.
+    Dismissed,
+    /// The notification was closed via a `CloseNotification` D-Bus call.
+    CloseCall,
+    /// Undefined/reserved reason.
+    Undefined,
+}
+
+impl CloseReason {
+    /// Returns the spec-defined reason code.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::Expired => 1,
+            Self::Dismissed => 2,
+            Self::CloseCall => 3,
+            Self::Undefined => 4,
+        }
+    }
 }
 
 /// Possible actions for a notification.
@@ -156,10 +279,36 @@ pub enum Action {
     Show(Notification),
     /// Show the last notification.
     ShowLast,
-    /// Close a notification.
-    Close(Option<u32>),
-    /// Close all the notifications.
-    CloseAll,
+    /// Re-show an existing notification from history, by ID.
+    ///
+    /// Unlike [`Action::Show`], this doesn't go through [`Manager::add`]: the existing entry is
+    /// marked unread in place and a window is (re)created for it, rather than being duplicated
+    /// as a new entry under the same ID. See [`crate::rofi`].
+    Reshow(u32),
+    /// Open the notification history browser (see [`crate::rofi`]).
+    BrowseHistory,
+    /// Close a notification, with the reason it was closed.
+    Close(Option<u32>, CloseReason),
+    /// Close all the notifications, with the reason they were closed.
+    CloseAll(CloseReason),
+    /// Invoke an action on a notification.
+    ///
+    /// Carries the notification ID and the invoked action's key.
+    InvokeAction(u32, String),
+    /// Overlay a `field.path=value` assignment onto the running configuration.
+    ///
+    /// See [`Config::apply_override`](crate::config::Config::apply_override).
+    SetConfig(String),
+}
+
+/// Result of [`Manager::add`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// The notification was added as a new, standalone entry.
+    Added,
+    /// The notification was coalesced into the still-unread entry with this ID, rather than
+    /// added as a new entry.
+    Coalesced(u32),
 }
 
 /// Notification manager.
@@ -167,24 +316,70 @@ pub enum Action {
 pub struct Manager {
     /// Inner type that holds the notifications in thread-safe way.
     inner: Arc<RwLock<Vec<Notification>>>,
+    /// Optional on-disk history backend; notifications are written through to it as they
+    /// change, and rehydrated from it on [`Manager::init`].
+    store: Option<Arc<HistoryStore>>,
+    /// Next sequence number to hand out in [`Manager::add`], kept monotonic across restarts by
+    /// resuming past the highest `seq` rehydrated from `store`.
+    next_seq: Arc<RwLock<u64>>,
 }
 
 impl Clone for Manager {
     fn clone(&self) -> Self {
         Self {
             inner: Arc::clone(&self.inner),
+            store: self.store.clone(),
+            next_seq: Arc::clone(&self.next_seq),
         }
     }
 }
 
 impl Manager {
-    /// Initializes the notification manager.
-    pub fn init() -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(Vec::new())),
+    /// Initializes the notification manager, rehydrating history from `store` (if given).
+    pub fn init(store: Option<HistoryStore>) -> Result<Self> {
+        let store = store.map(Arc::new);
+        let notifications = match &store {
+            Some(store) => store.load()?,
+            None => Vec::new(),
+        };
+        let next_seq = notifications.iter().map(|n| n.seq).max().unwrap_or(0) + 1;
+        Ok(Self {
+            inner: Arc::new(RwLock::new(notifications)),
+            store,
+            next_seq: Arc::new(RwLock::new(next_seq)),
+        })
+    }
+
+    /// Writes `notification` through to the history backend, if one is configured.
+    fn persist(&self, notification: &Notification) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.write_through(notification) {
+                tracing::warn!("failed to persist notification history: {}", e);
+            }
         }
     }
 
+    /// Returns the notifications whose `timestamp` falls within `[since, until]` and that match
+    /// `filter` (if given), most recent first, for paging through history.
+    pub fn query(
+        &self,
+        since: u64,
+        until: u64,
+        filter: Option<&NotificationFilter>,
+    ) -> Vec<Notification> {
+        let mut notifications: Vec<Notification> = self
+            .inner
+            .read()
+            .expect("failed to retrieve notifications")
+            .iter()
+            .filter(|n| n.timestamp >= since && n.timestamp <= until)
+            .filter(|n| filter.map(|f| n.matches_filter(f)).unwrap_or(true))
+            .cloned()
+            .collect();
+        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications
+    }
+
     /// Returns the number of notifications.
     pub fn count(&self) -> usize {
         self.inner
@@ -193,12 +388,89 @@ impl Manager {
             .len()
     }
 
-    /// Adds a new notifications to manage.
-    pub fn add(&self, notification: Notification) {
+    /// Returns the highest notification ID currently stored, or 0 if empty.
+    ///
+    /// Used to seed the D-Bus `next_id` counter above any ID rehydrated from history, so a
+    /// freshly assigned ID can't collide with a stale (and possibly still-unread) entry that
+    /// was loaded on [`Manager::init`].
+    pub fn max_id(&self) -> u32 {
         self.inner
-            .write()
+            .read()
             .expect("failed to retrieve notifications")
-            .push(notification);
+            .iter()
+            .map(|n| n.id)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Adds `notification` to manage.
+    ///
+    /// If `coalesce_window` is `Some` and an unread notification sharing `notification`'s
+    /// [`coalesce_key`](Notification::coalesce_key) was shown within that window, `notification`
+    /// is folded into it in place (refreshing `timestamp` and `body`, and bumping `stack_count`)
+    /// instead of being added as a new entry.
+    pub fn add(
+        &self,
+        mut notification: Notification,
+        coalesce_window: Option<Duration>,
+        rules: &[NotificationFilter],
+    ) -> AddOutcome {
+        let (outcome, persisted) = {
+            let mut notifications = self
+                .inner
+                .write()
+                .expect("failed to retrieve notifications");
+            let existing = coalesce_window.and_then(|window| {
+                let key = notification.coalesce_key(rules);
+                notifications.iter_mut().find(|n| {
+                    !n.is_read
+                        && n.coalesce_key(rules) == key
+                        && notification.timestamp.saturating_sub(n.timestamp) <= window.as_secs()
+                })
+            });
+            match existing {
+                Some(existing) => {
+                    existing.timestamp = notification.timestamp;
+                    existing.body = notification.body;
+                    existing.expire_timeout = notification.expire_timeout;
+                    existing.stack_count += 1;
+                    (AddOutcome::Coalesced(existing.id), existing.clone())
+                }
+                None => {
+                    notification.seq = self.next_seq();
+                    notifications.push(notification.clone());
+                    (AddOutcome::Added, notification)
+                }
+            }
+        };
+        self.persist(&persisted);
+        outcome
+    }
+
+    /// Hands out the next sequence number, advancing the counter.
+    fn next_seq(&self) -> u64 {
+        let mut next_seq = self.next_seq.write().expect("failed to retrieve sequence counter");
+        let seq = *next_seq;
+        *next_seq += 1;
+        seq
+    }
+
+    /// Returns the notifications added after `last_seq`, oldest first, along with the latest
+    /// sequence number handed out so far (unchanged from `last_seq` if nothing new was added),
+    /// for a caller to remember and pass back on its next call.
+    ///
+    /// This is the pull side of the `NotificationAdded` signal: a client that was disconnected
+    /// can call this once it reconnects to catch up without missing or double-counting entries.
+    pub fn poll_since(&self, last_seq: u64) -> (u64, Vec<Notification>) {
+        let notifications = self.inner.read().expect("failed to retrieve notifications");
+        let mut new: Vec<Notification> = notifications
+            .iter()
+            .filter(|n| n.seq > last_seq)
+            .cloned()
+            .collect();
+        new.sort_by_key(|n| n.seq);
+        let latest_seq = new.last().map(|n| n.seq).unwrap_or(last_seq);
+        (latest_seq, new)
     }
 
     /// Returns the last unread notification.
@@ -212,14 +484,21 @@ impl Manager {
     }
 
     /// Marks the last notification as read.
-    pub fn mark_last_as_read(&self) {
-        let mut notifications = self
-            .inner
-            .write()
-            .expect("failed to retrieve notifications");
-        if let Some(notification) = notifications.iter_mut().filter(|v| !v.is_read).last() {
+    ///
+    /// Returns the ID of the notification that was marked, if any.
+    pub fn mark_last_as_read(&self) -> Option<u32> {
+        let persisted = {
+            let mut notifications = self
+                .inner
+                .write()
+                .expect("failed to retrieve notifications");
+            let notification = notifications.iter_mut().filter(|v| !v.is_read).last()?;
             notification.is_read = true;
-        }
+            notification.clone()
+        };
+        let id = persisted.id;
+        self.persist(&persisted);
+        Some(id)
     }
 
     /// Marks the next notification as unread starting from the first one.
@@ -248,25 +527,57 @@ impl Manager {
 
     /// Marks the given notification as read.
     pub fn mark_as_read(&self, id: u32) {
-        let mut notifications = self
-            .inner
-            .write()
-            .expect("failed to retrieve notifications");
-        if let Some(notification) = notifications
-            .iter_mut()
-            .find(|notification| notification.id == id)
-        {
+        let persisted = {
+            let mut notifications = self
+                .inner
+                .write()
+                .expect("failed to retrieve notifications");
+            let Some(notification) = notifications
+                .iter_mut()
+                .find(|notification| notification.id == id)
+            else {
+                return;
+            };
             notification.is_read = true;
-        }
+            notification.clone()
+        };
+        self.persist(&persisted);
+    }
+
+    /// Marks the given notification as unread again, e.g. to re-show it from history.
+    ///
+    /// Returns the notification, if it exists, so the caller can (re)create a window for it
+    /// in place, rather than going through [`Manager::add`] and duplicating the entry under
+    /// the same ID.
+    pub fn mark_unread(&self, id: u32) -> Option<Notification> {
+        let persisted = {
+            let mut notifications = self
+                .inner
+                .write()
+                .expect("failed to retrieve notifications");
+            let notification = notifications
+                .iter_mut()
+                .find(|notification| notification.id == id)?;
+            notification.is_read = false;
+            notification.clone()
+        };
+        self.persist(&persisted);
+        Some(persisted)
     }
 
     /// Marks all the notifications as read.
     pub fn mark_all_as_read(&self) {
-        let mut notifications = self
-            .inner
-            .write()
-            .expect("failed to retrieve notifications");
-        notifications.iter_mut().for_each(|v| v.is_read = true);
+        let persisted = {
+            let mut notifications = self
+                .inner
+                .write()
+                .expect("failed to retrieve notifications");
+            notifications.iter_mut().for_each(|v| v.is_read = true);
+            notifications.clone()
+        };
+        for notification in &persisted {
+            self.persist(notification);
+        }
     }
 
     /// Returns the number of unread notifications.
@@ -284,6 +595,31 @@ impl Manager {
             .map(|v| !v.is_read)
             .unwrap_or_default()
     }
+
+    /// Returns a snapshot of all stored notifications, in insertion order.
+    pub fn all(&self) -> Vec<Notification> {
+        self.inner.read().expect("failed to retrieve notifications").clone()
+    }
+
+    /// Returns the notification with the given ID, if any.
+    pub fn get(&self, id: u32) -> Option<Notification> {
+        self.inner
+            .read()
+            .expect("failed to retrieve notifications")
+            .iter()
+            .find(|notification| notification.id == id)
+            .cloned()
+    }
+
+    /// Returns the IDs of the currently unread notifications.
+    pub fn unread_ids(&self) -> Vec<u32> {
+        let notifications = self.inner.read().expect("failed to retrieve notifications");
+        notifications
+            .iter()
+            .filter(|v| !v.is_read)
+            .map(|v| v.id)
+            .collect()
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,48 @@
+use crate::config::RateLimitConfig;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// A single `app_name`'s token bucket.
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-`app_name` token-bucket rate limiter, used in [`DeliveryMode::Queued`](crate::config::DeliveryMode::Queued)
+/// mode to stop a single misbehaving sender from monopolizing the display.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<String, TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter from `config`.
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Attempts to consume one token for `app_name`, refilling first based on elapsed time.
+    ///
+    /// Returns `true` if a token was available (i.e. the notification should be let through),
+    /// `false` if `app_name` is currently throttled.
+    pub fn try_acquire(&mut self, app_name: &str) -> bool {
+        let now = Instant::now();
+        let bucket = self.buckets.entry(app_name.to_string()).or_insert_with(|| TokenBucket {
+            tokens: self.config.burst as f64,
+            last_refill: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.refill_per_sec as f64)
+            .min(self.config.burst as f64);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::config::MenuConfig;
+use crate::error::{Error, Result};
+use crate::notification::{Action, Manager};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// A single menu entry: the label shown to the user, and the [`Action`] it resolves to if
+/// picked.
+///
+/// Keeping these paired, rather than encoding the routing info into the label text itself,
+/// means the menu program only ever displays clean, human-readable lines.
+struct Entry {
+    label: String,
+    action: Action,
+}
+
+/// Renders the [`Manager`]'s stored notifications (and their actions) into a list, pipes it
+/// to the configured external menu program, and resolves the user's pick into an [`Action`].
+///
+/// Every notification contributes a "re-show" entry, plus one additional entry per action it
+/// carries. Picking a re-show entry re-displays the notification; picking an action entry
+/// invokes that action, exactly as if the user had clicked it on the notification itself.
+pub fn browse_history(menu: &MenuConfig, manager: &Manager) -> Result<Option<Action>> {
+    let notifications = manager.all();
+    if notifications.is_empty() {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::new();
+    for notification in &notifications {
+        entries.push(Entry {
+            label: format!("{} — {}", notification.app_name, notification.summary),
+            action: Action::Reshow(notification.id),
+        });
+        for (key, label) in &notification.actions {
+            entries.push(Entry {
+                label: format!(
+                    "{} — {} ▸ {}",
+                    notification.app_name, notification.summary, label
+                ),
+                action: Action::InvokeAction(notification.id, key.clone()),
+            });
+        }
+    }
+
+    let menu_input: String = entries
+        .iter()
+        .map(|entry| entry.label.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let selection = run_menu(menu, &menu_input)?;
+    let selection = selection.trim();
+    if selection.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(entries
+        .into_iter()
+        .find(|entry| entry.label == selection)
+        .map(|entry| entry.action))
+}
+
+/// Spawns the configured menu program, writes `input` to its stdin and returns its stdout.
+fn run_menu(menu: &MenuConfig, input: &str) -> Result<String> {
+    let mut child = Command::new(&menu.command)
+        .args(&menu.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| Error::Init(String::from("failed to open menu program's stdin")))?
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
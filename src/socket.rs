@@ -0,0 +1,129 @@
+use crate::error::Result;
+use crate::notification::{Action, CloseReason};
+use calloop::channel::Sender;
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+
+/// Name of the control socket file, relative to `$XDG_RUNTIME_DIR`.
+const SOCKET_NAME: &str = concat!(env!("CARGO_PKG_NAME"), ".sock");
+
+/// Environment variable that overrides the control socket path, for both the daemon and the
+/// `runst msg` client.
+const SOCKET_ENV: &str = "RUNST_SOCKET";
+
+/// Returns the path the control socket is (or should be) bound at: `$RUNST_SOCKET` if set,
+/// otherwise `$XDG_RUNTIME_DIR/runst.sock`, falling back to the system temp directory.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    runtime_dir.join(SOCKET_NAME)
+}
+
+/// Unix-socket control server.
+///
+/// Accepts simple line-based commands and forwards them as [`Action`]s, giving users a
+/// dependency-free way to script `runst` from shell scripts and keybindings without linking
+/// against D-Bus or zbus.
+pub struct Socket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Socket {
+    /// Binds the control socket under `$XDG_RUNTIME_DIR` (falling back to the system temp
+    /// directory if it is not set).
+    pub fn init() -> Result<Self> {
+        let path = socket_path();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        tracing::debug!("listening on control socket: {}", path.display());
+        Ok(Self { listener, path })
+    }
+
+    /// Returns the raw file descriptor of the listening socket.
+    ///
+    /// This is used to register the socket as a readiness-based event source (e.g. with
+    /// `calloop`) instead of blocking a dedicated thread on [`UnixListener::incoming`].
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.listener.as_raw_fd()
+    }
+
+    /// Accepts every connection that is currently pending, forwarding parsed commands onto
+    /// `sender`.
+    ///
+    /// Intended to be called once the listener's file descriptor is reported readable by an
+    /// event loop. Each connection is handled on its own thread and may send multiple
+    /// newline-separated commands before closing.
+    pub fn accept_pending(&self, sender: &Sender<Action>) -> Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &sender) {
+                            tracing::warn!("control socket connection error: {}", e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Reads newline-separated commands off `stream` and forwards them as [`Action`]s.
+fn handle_connection(stream: UnixStream, sender: &Sender<Action>) -> Result<()> {
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        match parse_command(line.trim()) {
+            Some(action) => sender.send(action)?,
+            None => tracing::warn!("unknown control socket command: `{}`", line),
+        }
+    }
+    Ok(())
+}
+
+/// Parses a line-based control command into an [`Action`].
+///
+/// Supported commands: `history`, `close [id]`, `close-all`, `invoke-action <id> <key>`,
+/// `config <field.path>=<value>`.
+fn parse_command(line: &str) -> Option<Action> {
+    if let Some(assignment) = line.strip_prefix("config ") {
+        return Some(Action::SetConfig(assignment.trim().to_string()));
+    }
+    let mut parts = line.split_whitespace();
+    match parts.next()? {
+        "history" => Some(Action::BrowseHistory),
+        "close" => match parts.next() {
+            Some(id) => id
+                .parse()
+                .ok()
+                .map(|id| Action::Close(Some(id), CloseReason::Dismissed)),
+            None => Some(Action::Close(None, CloseReason::Dismissed)),
+        },
+        "close-all" => Some(Action::CloseAll(CloseReason::Dismissed)),
+        "invoke-action" => {
+            let id = parts.next()?.parse().ok()?;
+            let key = parts.next()?.to_string();
+            Some(Action::InvokeAction(id, key))
+        }
+        _ => None,
+    }
+}
@@ -1,19 +1,20 @@
-use crate::config::{Config, GlobalConfig};
+use crate::config::{Config, Geometry, GlobalConfig};
 use crate::error::{Error, Result};
-use crate::notification::{Manager, Notification, NOTIFICATION_MESSAGE_TEMPLATE};
+use crate::notification::{Icon, Notification, NOTIFICATION_MESSAGE_TEMPLATE};
 use cairo::{
-    Context as CairoContext, XCBConnection as CairoXCBConnection, XCBDrawable, XCBSurface,
-    XCBVisualType,
+    Context as CairoContext, Format, ImageSurface, XCBConnection as CairoXCBConnection,
+    XCBDrawable, XCBSurface, XCBVisualType,
 };
 use colorsys::ColorAlpha;
 use pango::{Context as PangoContext, FontDescription, Layout as PangoLayout};
 use pangocairo::functions as pango_functions;
 use std::collections::HashMap;
 use std::error::Error as StdError;
-use std::sync::Arc;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::time::Duration;
 use tera::{Result as TeraResult, Tera, Value};
 use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
 use x11rb::protocol::{xproto::*, Event};
 use x11rb::xcb_ffi::XCBConnection;
 use x11rb::COPY_DEPTH_FROM_PARENT;
@@ -49,6 +50,74 @@ impl From<Visualtype> for xcb_visualtype_t {
     }
 }
 
+/// A connected monitor/output, as reported by the RANDR extension.
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    /// Output name, e.g. `"DP-1"`.
+    pub name: String,
+    /// Absolute X coordinate of the monitor's origin, in root-window space.
+    pub x: i16,
+    /// Absolute Y coordinate of the monitor's origin, in root-window space.
+    pub y: i16,
+    /// Width of the monitor, in pixels.
+    pub width: u16,
+    /// Height of the monitor, in pixels.
+    pub height: u16,
+    /// Whether this is the primary monitor.
+    pub primary: bool,
+}
+
+/// Translates `geometry` into absolute root-window coordinates, anchored to `monitor`'s
+/// origin (or its far edge, per [`Geometry::x_negative`]/[`Geometry::y_negative`]).
+fn absolute_position(monitor: &Monitor, geometry: &Geometry) -> (i16, i16) {
+    let x = if geometry.x_negative {
+        i32::from(monitor.x) + i32::from(monitor.width) - geometry.width as i32
+            - geometry.x as i32
+    } else {
+        i32::from(monitor.x) + geometry.x as i32
+    };
+    let y = if geometry.y_negative {
+        i32::from(monitor.y) + i32::from(monitor.height) - geometry.height as i32
+            - geometry.y as i32
+    } else {
+        i32::from(monitor.y) + geometry.y as i32
+    };
+    (x as i16, y as i16)
+}
+
+/// Atoms used to set EWMH/ICCCM window-manager hints on notification windows, interned once
+/// at connection setup.
+#[derive(Debug, Clone, Copy)]
+struct Atoms {
+    net_wm_window_type: Atom,
+    net_wm_window_type_notification: Atom,
+    net_wm_state: Atom,
+    net_wm_state_above: Atom,
+    net_wm_name: Atom,
+    utf8_string: Atom,
+}
+
+impl Atoms {
+    /// Interns all the atoms required for [`X11::set_window_type_hints`].
+    fn intern(connection: &impl Connection) -> Result<Self> {
+        let net_wm_window_type = connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE")?;
+        let net_wm_window_type_notification =
+            connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE_NOTIFICATION")?;
+        let net_wm_state = connection.intern_atom(false, b"_NET_WM_STATE")?;
+        let net_wm_state_above = connection.intern_atom(false, b"_NET_WM_STATE_ABOVE")?;
+        let net_wm_name = connection.intern_atom(false, b"_NET_WM_NAME")?;
+        let utf8_string = connection.intern_atom(false, b"UTF8_STRING")?;
+        Ok(Self {
+            net_wm_window_type: net_wm_window_type.reply()?.atom,
+            net_wm_window_type_notification: net_wm_window_type_notification.reply()?.atom,
+            net_wm_state: net_wm_state.reply()?.atom,
+            net_wm_state_above: net_wm_state_above.reply()?.atom,
+            net_wm_name: net_wm_name.reply()?.atom,
+            utf8_string: utf8_string.reply()?.atom,
+        })
+    }
+}
+
 /// Wrapper for X11 [`connection`] and [`screen`].
 ///
 /// [`connection`]: XCBConnection
@@ -57,6 +126,7 @@ pub struct X11 {
     connection: XCBConnection,
     cairo: CairoXCBConnection,
     screen: Screen,
+    atoms: Atoms,
 }
 
 unsafe impl Send for X11 {}
@@ -73,15 +143,94 @@ impl X11 {
         tracing::trace!("Screen root: {:?}", screen.root);
         let cairo =
             unsafe { CairoXCBConnection::from_raw_none(connection.get_raw_xcb_connection() as _) };
+        let atoms = Atoms::intern(&connection)?;
         Ok(Self {
             connection,
             screen,
             cairo,
+            atoms,
         })
     }
 
-    /// Creates a window.
-    pub fn create_window(&mut self, config: &GlobalConfig) -> Result<X11Window> {
+    /// Queries the connected monitors via the RANDR extension.
+    ///
+    /// Returns an error if RANDR is unavailable; [`base_position`](Self::base_position) then
+    /// falls back to the whole screen.
+    fn query_monitors(&self) -> Result<Vec<Monitor>> {
+        let reply = self
+            .connection
+            .get_monitors(self.screen.root, true)?
+            .reply()?;
+        reply
+            .monitors
+            .into_iter()
+            .map(|monitor| {
+                let name = self.connection.get_atom_name(monitor.name)?.reply()?.name;
+                Ok(Monitor {
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    x: monitor.x,
+                    y: monitor.y,
+                    width: monitor.width,
+                    height: monitor.height,
+                    primary: monitor.primary,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the monitor currently under the mouse pointer, if any.
+    fn pointer_monitor<'a>(&self, monitors: &'a [Monitor]) -> Result<Option<&'a Monitor>> {
+        let pointer = self.connection.query_pointer(self.screen.root)?.reply()?;
+        Ok(monitors.iter().find(|monitor| {
+            pointer.root_x >= monitor.x
+                && pointer.root_x < monitor.x + monitor.width as i16
+                && pointer.root_y >= monitor.y
+                && pointer.root_y < monitor.y + monitor.height as i16
+        }))
+    }
+
+    /// Selects the target monitor for `selector`, which is one of `"pointer"` (or empty),
+    /// `"primary"`, or a RANDR output name.
+    fn select_monitor<'a>(
+        &self,
+        monitors: &'a [Monitor],
+        selector: &str,
+    ) -> Result<Option<&'a Monitor>> {
+        match selector {
+            "" | "pointer" => self.pointer_monitor(monitors),
+            "primary" => Ok(monitors.iter().find(|monitor| monitor.primary)),
+            name => Ok(monitors.iter().find(|monitor| monitor.name == name)),
+        }
+    }
+
+    /// Resolves the configured monitor and returns the absolute root-window coordinates a
+    /// notification window should be created/stacked from.
+    pub fn base_position(&self, config: &GlobalConfig) -> Result<(i16, i16)> {
+        let monitors = self.query_monitors().unwrap_or_else(|e| {
+            tracing::warn!("failed to query RANDR monitors: {}", e);
+            Vec::new()
+        });
+        let monitor = self
+            .select_monitor(&monitors, &config.monitor)
+            .ok()
+            .flatten()
+            .cloned()
+            .unwrap_or(Monitor {
+                name: String::new(),
+                x: 0,
+                y: 0,
+                width: self.screen.width_in_pixels,
+                height: self.screen.height_in_pixels,
+                primary: true,
+            });
+        tracing::debug!("placing notification window on monitor: {:?}", monitor);
+        Ok(absolute_position(&monitor, &config.geometry))
+    }
+
+    /// Creates a window for the given notification ID.
+    pub fn create_window(&mut self, config: &GlobalConfig, notification_id: u32) -> Result<X11Window> {
+        let (x, y) = self.base_position(config)?;
+
         let visual_id = self.screen.root_visual;
         let mut visual_type = self
             .find_xcb_visualtype(visual_id)
@@ -93,8 +242,8 @@ impl X11 {
             COPY_DEPTH_FROM_PARENT,
             window_id,
             self.screen.root,
-            config.geometry.x.try_into()?,
-            config.geometry.y.try_into()?,
+            x,
+            y,
             config.geometry.width.try_into()?,
             config.geometry.height.try_into()?,
             0,
@@ -102,9 +251,12 @@ impl X11 {
             visual_id,
             &CreateWindowAux::new()
                 .border_pixel(self.screen.white_pixel)
-                .override_redirect(1)
-                .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS),
+                .override_redirect(u8::from(!config.window_type_hint))
+                .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS | EventMask::KEY_PRESS),
         )?;
+        if config.window_type_hint {
+            self.set_window_type_hints(window_id)?;
+        }
         let surface = XCBSurface::create(
             &self.cairo,
             &XCBDrawable(window_id),
@@ -112,15 +264,51 @@ impl X11 {
             config.geometry.width.try_into()?,
             config.geometry.height.try_into()?,
         )?;
-        let context = CairoContext::new(&surface)?;
         X11Window::new(
             window_id,
-            context,
+            notification_id,
+            surface,
             &config.font,
             Box::leak(config.template.to_string().into_boxed_str()),
         )
     }
 
+    /// Sets the `_NET_WM_WINDOW_TYPE`, `_NET_WM_STATE`, `WM_CLASS` and `_NET_WM_NAME` hints on
+    /// `window_id`, so that compositing/tiling window managers recognize it as a notification
+    /// (rather than a regular override-redirect popup) and can animate/shadow it accordingly.
+    fn set_window_type_hints(&self, window_id: u32) -> Result<()> {
+        self.connection.change_property32(
+            PropMode::REPLACE,
+            window_id,
+            self.atoms.net_wm_window_type,
+            AtomEnum::ATOM,
+            &[self.atoms.net_wm_window_type_notification],
+        )?;
+        self.connection.change_property32(
+            PropMode::REPLACE,
+            window_id,
+            self.atoms.net_wm_state,
+            AtomEnum::ATOM,
+            &[self.atoms.net_wm_state_above],
+        )?;
+        let class = concat!(env!("CARGO_PKG_NAME"), "\0", env!("CARGO_PKG_NAME"), "\0");
+        self.connection.change_property8(
+            PropMode::REPLACE,
+            window_id,
+            AtomEnum::WM_CLASS,
+            AtomEnum::STRING,
+            class.as_bytes(),
+        )?;
+        self.connection.change_property8(
+            PropMode::REPLACE,
+            window_id,
+            self.atoms.net_wm_name,
+            self.atoms.utf8_string,
+            env!("CARGO_PKG_NAME").as_bytes(),
+        )?;
+        Ok(())
+    }
+
     /// Find a `xcb_visualtype_t` based on its ID number
     fn find_xcb_visualtype(&self, visual_id: u32) -> Option<xcb_visualtype_t> {
         for root in &self.connection.setup().roots {
@@ -149,39 +337,65 @@ impl X11 {
         Ok(())
     }
 
-    /// Handles the events.
-    pub fn handle_events<F>(
+    /// Unmaps and destroys the given X11 window, removing it from the notification stack.
+    pub fn destroy_window(&self, window: &X11Window) -> Result<()> {
+        self.connection.unmap_window(window.id)?;
+        self.connection.destroy_window(window.id)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// Moves the given X11 window to `(x, y)` in root-window coordinates, e.g. to re-flow the
+    /// notification stack after a window above/below it was added or removed.
+    pub fn reposition_window(&self, window: &X11Window, x: i16, y: i16) -> Result<()> {
+        self.connection.configure_window(
+            window.id,
+            &ConfigureWindowAux::new().x(x.into()).y(y.into()),
+        )?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// Returns the current height of the given window, reflecting any `wrap_content` resize
+    /// performed by a previous [`draw_window`](Self::draw_window) call.
+    pub fn window_height(&self, window: &X11Window) -> Result<u16> {
+        Ok(self.connection.get_geometry(window.id)?.reply()?.height)
+    }
+
+    /// Draws `notification` into `window`.
+    pub fn draw_window(
         &self,
-        window: Arc<X11Window>,
-        manager: Manager,
-        config: Arc<Config>,
-        on_press: F,
-    ) -> Result<()>
-    where
-        F: Fn(&Notification),
-    {
-        loop {
-            self.connection.flush()?;
-            let event = self.connection.wait_for_event()?;
-            let mut event_opt = Some(event);
-            while let Some(event) = event_opt {
-                tracing::trace!("New event: {:?}", event);
-                match event {
-                    Event::Expose(_) => {
-                        let notification = manager.get_last_unread();
-                        let unread_count = manager.get_unread_count();
-                        window.draw(&self.connection, notification, unread_count, &config)?;
-                    }
-                    Event::ButtonPress(_) => {
-                        let notification = manager.get_last_unread();
-                        manager.mark_last_as_read();
-                        on_press(&notification);
-                    }
-                    _ => {}
-                }
-                event_opt = self.connection.poll_for_event()?;
-            }
+        window: &X11Window,
+        notification: Notification,
+        unread_count: usize,
+        config: &Config,
+    ) -> Result<()> {
+        window.draw(&self.connection, notification, unread_count, config)
+    }
+
+    /// Returns the raw file descriptor of the underlying X11 connection.
+    ///
+    /// This is used to register the connection as a readiness-based event source (e.g. with
+    /// `calloop`) instead of blocking a dedicated thread on [`Connection::wait_for_event`].
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.connection.as_raw_fd()
+    }
+
+    /// Drains and returns all X11 events that are currently pending, without interpreting
+    /// them.
+    ///
+    /// Intended to be called once the connection's file descriptor is reported readable by an
+    /// event loop, rather than blocking on [`Connection::wait_for_event`]. Interpreting events
+    /// (matching a window to a notification, dispatching actions) is left to the caller, since
+    /// with multiple stacked windows that requires cross-referencing the notification manager.
+    pub fn poll_events(&self) -> Result<Vec<Event>> {
+        self.connection.flush()?;
+        let mut events = Vec::new();
+        while let Some(event) = self.connection.poll_for_event()? {
+            tracing::trace!("New event: {:?}", event);
+            events.push(event);
         }
+        Ok(events)
     }
 }
 
@@ -189,8 +403,14 @@ impl X11 {
 pub struct X11Window {
     /// Window ID.
     pub id: u32,
-    /// Graphics renderer context.
-    pub cairo_context: CairoContext,
+    /// ID of the notification this window is displaying.
+    pub notification_id: u32,
+    /// Cairo surface backed by the window itself, i.e. the front buffer.
+    ///
+    /// [`draw`](Self::draw) never paints onto this directly; it renders into an off-screen
+    /// [`ImageSurface`] and blits the finished frame here in one operation, so an observer
+    /// (compositor or the user) never sees a half-cleared frame.
+    front_surface: XCBSurface,
     /// Text renderer context.
     pub pango_context: PangoContext,
     /// Window layout.
@@ -204,13 +424,15 @@ unsafe impl Sync for X11Window {}
 
 impl X11Window {
     /// Creates a new instance of window.
-    pub fn new(
+    fn new(
         id: u32,
-        cairo_context: CairoContext,
+        notification_id: u32,
+        front_surface: XCBSurface,
         font: &str,
         raw_template: &'static str,
     ) -> Result<Self> {
-        let pango_context = pango_functions::create_context(&cairo_context);
+        let bootstrap_context = CairoContext::new(&front_surface)?;
+        let pango_context = pango_functions::create_context(&bootstrap_context);
         let layout = PangoLayout::new(&pango_context);
         let font_description = FontDescription::from_string(font);
         pango_context.set_font_description(Some(&font_description));
@@ -234,7 +456,8 @@ impl X11Window {
         );
         Ok(Self {
             id,
-            cairo_context,
+            notification_id,
+            front_surface,
             pango_context,
             layout,
             template,
@@ -254,6 +477,10 @@ impl X11Window {
     }
 
     /// Draws the window content.
+    ///
+    /// Renders into an off-screen [`ImageSurface`] back buffer sized to the window's current
+    /// geometry, then blits the finished frame onto [`front_surface`](Self::front_surface) in
+    /// a single `paint`, so the window is never visible mid-repaint.
     fn draw(
         &self,
         connection: &XCBConnection,
@@ -265,23 +492,51 @@ impl X11Window {
         urgency_config.run_commands(&notification)?;
         let message =
             notification.render_message(&self.template, urgency_config.text, unread_count)?;
+
+        let geometry = connection.get_geometry(self.id)?.reply()?;
+        let back_surface =
+            ImageSurface::create(Format::ARgb32, geometry.width.into(), geometry.height.into())?;
+        let back_context = CairoContext::new(&back_surface)?;
+
         let background_color = urgency_config.background;
-        self.cairo_context.set_source_rgba(
+        back_context.set_source_rgba(
             background_color.red() / 255.0,
             background_color.green() / 255.0,
             background_color.blue() / 255.0,
             background_color.alpha(),
         );
-        self.cairo_context.fill()?;
-        self.cairo_context.paint()?;
+        back_context.paint()?;
+
+        let mut text_x_offset = 0.;
+        if config.global.icon.enabled {
+            if let Some(icon) = notification.icon.as_ref() {
+                match build_icon_surface(icon) {
+                    Ok(Some(surface)) => {
+                        let size = f64::from(config.global.icon.size);
+                        let (width, height) = (f64::from(surface.width()), f64::from(surface.height()));
+                        if width > 0. && height > 0. {
+                            back_context.save()?;
+                            back_context.scale(size / width, size / height);
+                            back_context.set_source_surface(&surface, 0., 0.)?;
+                            back_context.paint()?;
+                            back_context.restore()?;
+                            text_x_offset = size + f64::from(config.global.icon.padding);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("failed to render notification icon: {}", e),
+                }
+            }
+        }
+
         let foreground_color = urgency_config.foreground;
-        self.cairo_context.set_source_rgba(
+        back_context.set_source_rgba(
             foreground_color.red() / 255.0,
             foreground_color.green() / 255.0,
             foreground_color.blue() / 255.0,
             foreground_color.alpha(),
         );
-        self.cairo_context.move_to(0., 0.);
+        back_context.move_to(text_x_offset, 0.);
         self.layout.set_markup(&message);
         if config.global.wrap_content {
             let (width, height) = self.layout.pixel_size();
@@ -290,7 +545,87 @@ impl X11Window {
                 .height(height.try_into().ok());
             connection.configure_window(self.id, &values)?;
         }
-        pango_functions::show_layout(&self.cairo_context, &self.layout);
+        pango_functions::show_layout(&back_context, &self.layout);
+        back_surface.flush();
+
+        let front_context = CairoContext::new(&self.front_surface)?;
+        front_context.set_source_surface(&back_surface, 0., 0.)?;
+        front_context.paint()?;
+        self.front_surface.flush();
         Ok(())
     }
 }
+
+/// Builds a [`cairo::ImageSurface`] for the given [`Icon`], returning `Ok(None)` if the icon
+/// could not be loaded (e.g. a missing file or an unsupported format) rather than failing the
+/// whole draw.
+fn build_icon_surface(icon: &Icon) -> Result<Option<ImageSurface>> {
+    match icon {
+        Icon::Data {
+            width,
+            height,
+            rowstride,
+            has_alpha,
+            channels,
+            data,
+        } => {
+            let (width, height, rowstride, channels) = (*width, *height, *rowstride, *channels);
+            if width <= 0 || height <= 0 || rowstride <= 0 || channels <= 0 {
+                tracing::debug!("ignoring image-data hint with non-positive dimensions");
+                return Ok(None);
+            }
+            // The untrusted D-Bus caller controls all of `width`/`height`/`rowstride`/`channels`,
+            // so check the claimed dimensions actually fit `data` before indexing into it below —
+            // a mismatched tuple (honest mistake or not) must not be able to panic the daemon.
+            let bytes_per_pixel = if *has_alpha { 4 } else { 3 };
+            let required_len = (height as usize - 1) * rowstride as usize
+                + (width as usize - 1) * channels as usize
+                + bytes_per_pixel;
+            if data.len() < required_len {
+                tracing::debug!(
+                    "ignoring image-data hint: {} byte(s) is too small for {}x{} at stride {} ({} needed)",
+                    data.len(),
+                    width,
+                    height,
+                    rowstride,
+                    required_len
+                );
+                return Ok(None);
+            }
+            let mut surface = ImageSurface::create(Format::ARgb32, width, height)?;
+            let stride = surface.stride() as usize;
+            {
+                let mut surface_data = surface.data()?;
+                for y in 0..height as usize {
+                    for x in 0..width as usize {
+                        let src = y * rowstride as usize + x * channels as usize;
+                        let (r, g, b, a) = if *has_alpha {
+                            (data[src], data[src + 1], data[src + 2], data[src + 3])
+                        } else {
+                            (data[src], data[src + 1], data[src + 2], 255)
+                        };
+                        // cairo's `ARgb32` format stores premultiplied, native-endian BGRA.
+                        let alpha = f64::from(a) / 255.0;
+                        let dst = y * stride + x * 4;
+                        surface_data[dst] = (f64::from(b) * alpha) as u8;
+                        surface_data[dst + 1] = (f64::from(g) * alpha) as u8;
+                        surface_data[dst + 2] = (f64::from(r) * alpha) as u8;
+                        surface_data[dst + 3] = a;
+                    }
+                }
+            }
+            Ok(Some(surface))
+        }
+        Icon::Path(path) => match std::fs::File::open(path).and_then(|mut file| {
+            ImageSurface::create_from_png(&mut file).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+            })
+        }) {
+            Ok(surface) => Ok(Some(surface)),
+            Err(e) => {
+                tracing::debug!("could not load icon from `{}`: {}", path, e);
+                Ok(None)
+            }
+        },
+    }
+}
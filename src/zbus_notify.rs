@@ -1,8 +1,11 @@
 use zbus::{interface, fdo};
 use zbus::object_server::SignalEmitter;
+use calloop::channel::Sender;
 use std::collections::HashMap;
-use std::sync::mpsc::Sender;
-use crate::notification::{Action, Notification, Urgency};
+use crate::history::HistoryEntry;
+use crate::notification::{
+    Action, CloseReason, Icon, Manager, Notification, NotificationFilter, Urgency,
+};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub struct Notifications {
@@ -11,9 +14,13 @@ pub struct Notifications {
 }
 
 impl Notifications {
-    pub fn new(sender: Sender<Action>) -> Self {
+    /// Creates a new handler, handing out IDs starting above `start_id`.
+    ///
+    /// `start_id` should be [`Manager::max_id`] at startup time, so a freshly assigned ID can't
+    /// collide with one rehydrated from history after a restart.
+    pub fn new(sender: Sender<Action>, start_id: u32) -> Self {
         Self {
-            next_id: std::sync::Arc::new(std::sync::Mutex::new(0)),
+            next_id: std::sync::Arc::new(std::sync::Mutex::new(start_id)),
             sender,
         }
     }
@@ -31,17 +38,22 @@ impl Notifications {
     }
 
     async fn get_capabilities(&self) -> fdo::Result<Vec<String>> {
-        Ok(vec!["body".to_string(), "body-markup".to_string()])
+        Ok(vec![
+            "body".to_string(),
+            "body-markup".to_string(),
+            "actions".to_string(),
+            "icon-static".to_string(),
+        ])
     }
 
     async fn notify(
         &self,
         app_name: String,
         replaces_id: u32,
-        _app_icon: String,
+        app_icon: String,
         summary: String,
         body: String,
-        _actions: Vec<String>,
+        actions: Vec<String>,
         hints: HashMap<String, zbus::zvariant::Value<'_>>,
         expire_timeout: i32,
     ) -> fdo::Result<u32> {
@@ -70,6 +82,15 @@ impl Notifications {
             .unwrap()
             .as_secs();
 
+        // The actions vector is a flat list of `[key, display_label, key, display_label, ...]`
+        // pairs, with the special "default" key invoked on a plain click.
+        let actions = actions
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+
+        let icon = parse_icon(&app_icon, &hints);
+
         let notification = Notification {
             id,
             app_name,
@@ -79,6 +100,10 @@ impl Notifications {
             urgency,
             is_read: false,
             timestamp,
+            actions,
+            icon,
+            stack_count: 1,
+            seq: 0,
         };
 
         self.sender
@@ -90,7 +115,7 @@ impl Notifications {
 
     async fn close_notification(&self, id: u32) -> fdo::Result<()> {
         self.sender
-            .send(Action::Close(Some(id)))
+            .send(Action::Close(Some(id), CloseReason::CloseCall))
             .map_err(|e| fdo::Error::Failed(format!("Close failed: {}", e)))?;
         Ok(())
     }
@@ -108,15 +133,172 @@ impl Notifications {
         id: u32,
         action_key: String,
     ) -> zbus::Result<()>;
+
+    /// Fires whenever a new notification is added, carrying its sequence number and
+    /// summary/body payload.
+    ///
+    /// Push-side counterpart to [`NotificationControl::poll_since`]: subscribers may listen to
+    /// this signal instead of polling, or use it just to know when to poll.
+    #[zbus(signal)]
+    async fn notification_added(
+        signal_emitter: &SignalEmitter<'_>,
+        seq: u64,
+        summary: String,
+        body: String,
+    ) -> zbus::Result<()>;
+}
+
+/// Parses the `image-data`/`icon_data` or `image-path` hints (in that priority order, per the
+/// spec), falling back to the `app_icon` argument if neither hint is present.
+fn parse_icon(app_icon: &str, hints: &HashMap<String, zbus::zvariant::Value<'_>>) -> Option<Icon> {
+    if let Some(value) = hints
+        .get("image-data")
+        .or_else(|| hints.get("image_data"))
+        .or_else(|| hints.get("icon_data"))
+    {
+        if let Ok(fields) = zbus::zvariant::Structure::try_from(value.clone()) {
+            let fields = fields.into_fields();
+            if let [width, height, rowstride, has_alpha, _bits_per_sample, channels, data] =
+                &fields[..]
+            {
+                if let (Ok(width), Ok(height), Ok(rowstride), Ok(has_alpha), Ok(channels), Ok(data)) = (
+                    i32::try_from(width.clone()),
+                    i32::try_from(height.clone()),
+                    i32::try_from(rowstride.clone()),
+                    bool::try_from(has_alpha.clone()),
+                    i32::try_from(channels.clone()),
+                    Vec::<u8>::try_from(data.clone()),
+                ) {
+                    return Some(Icon::Data {
+                        width,
+                        height,
+                        rowstride,
+                        has_alpha,
+                        channels,
+                        data,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(path) = hints
+        .get("image-path")
+        .or_else(|| hints.get("image_path"))
+        .and_then(|v| String::try_from(v.clone()).ok())
+    {
+        return Some(Icon::Path(resolve_icon_path(&path)));
+    }
+
+    if !app_icon.is_empty() {
+        return Some(Icon::Path(resolve_icon_path(app_icon)));
+    }
+
+    None
+}
+
+/// Icon theme directories searched, in order, for a themed icon name that isn't already a
+/// path/URI, per the [icon theme spec](https://specifications.freedesktop.org/icon-theme-spec/latest/).
+///
+/// `scalable` is deliberately not searched: icon rendering (`crate::x11`) only loads icons via
+/// `ImageSurface::create_from_png`, and most icon themes ship their primary icons as SVG under
+/// `hicolor/scalable/apps`, so preferring it here would typically resolve to a path that then
+/// silently fails to load.
+const ICON_THEME_DIRS: &[&str] = &[
+    "/usr/share/icons/hicolor/256x256/apps",
+    "/usr/share/icons/hicolor/128x128/apps",
+    "/usr/share/icons/hicolor/64x64/apps",
+    "/usr/share/icons/hicolor/48x48/apps",
+    "/usr/share/icons/hicolor/32x32/apps",
+    "/usr/share/pixmaps",
+];
+
+/// Extensions tried, in order, for each [`ICON_THEME_DIRS`] entry.
+///
+/// `svg` is deliberately excluded: there is no SVG rasterizer in the tree, only
+/// `ImageSurface::create_from_png`.
+const ICON_THEME_EXTENSIONS: &[&str] = &["png", "xpm"];
+
+/// Resolves `name` to a file path: passed through unchanged if it's already an absolute path or
+/// a `file://` URI, otherwise looked up as a themed icon name under [`ICON_THEME_DIRS`].
+///
+/// Falls back to returning `name` as-is if it can't be resolved, so a later, direct load attempt
+/// still gets a sensible error to log rather than silently dropping the icon.
+fn resolve_icon_path(name: &str) -> String {
+    if name.starts_with('/') {
+        return name.to_string();
+    }
+    if let Some(path) = name.strip_prefix("file://") {
+        return path.to_string();
+    }
+    for dir in ICON_THEME_DIRS {
+        for ext in ICON_THEME_EXTENSIONS {
+            let candidate = format!("{}/{}.{}", dir, name, ext);
+            if std::path::Path::new(&candidate).is_file() {
+                return candidate;
+            }
+        }
+    }
+    name.to_string()
+}
+
+/// Handle for emitting `Notifications` signals from outside of an interface method.
+///
+/// The zbus server runs on its own Tokio runtime in a dedicated thread, but signals such as
+/// `ActionInvoked` need to be emitted in response to events observed on the (synchronous)
+/// main event loop. This bundles a [`SignalEmitter`] for the notifications object together
+/// with a handle to that Tokio runtime, mirroring how `notify-rust` keeps the D-Bus
+/// connection alive and reachable for emitting signals on demand.
+#[derive(Clone)]
+pub struct DbusHandle {
+    runtime: tokio::runtime::Handle,
+    emitter: SignalEmitter<'static>,
+}
+
+impl DbusHandle {
+    /// Creates a new handle from a running Tokio runtime and a signal emitter.
+    pub fn new(runtime: tokio::runtime::Handle, emitter: SignalEmitter<'static>) -> Self {
+        Self { runtime, emitter }
+    }
+
+    /// Emits the `ActionInvoked` signal for the given notification ID and action key.
+    pub fn emit_action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()> {
+        self.runtime
+            .block_on(Notifications::action_invoked(&self.emitter, id, action_key))
+    }
+
+    /// Emits the `NotificationClosed` signal for the given notification ID and reason.
+    pub fn emit_notification_closed(&self, id: u32, reason: CloseReason) -> zbus::Result<()> {
+        self.runtime.block_on(Notifications::notification_closed(
+            &self.emitter,
+            id,
+            reason.code(),
+        ))
+    }
+
+    /// Emits the `NotificationAdded` signal for a newly added notification.
+    pub fn emit_notification_added(
+        &self,
+        seq: u64,
+        summary: String,
+        body: String,
+    ) -> zbus::Result<()> {
+        self.runtime
+            .block_on(Notifications::notification_added(&self.emitter, seq, summary, body))
+    }
 }
 
 pub struct NotificationControl {
     sender: Sender<Action>,
+    notifications: Manager,
 }
 
 impl NotificationControl {
-    pub fn new(sender: Sender<Action>) -> Self {
-        Self { sender }
+    pub fn new(sender: Sender<Action>, notifications: Manager) -> Self {
+        Self {
+            sender,
+            notifications,
+        }
     }
 }
 
@@ -124,22 +306,74 @@ impl NotificationControl {
 impl NotificationControl {
     async fn history(&self) -> fdo::Result<()> {
         self.sender
-            .send(Action::ShowLast)
+            .send(Action::BrowseHistory)
             .map_err(|e| fdo::Error::Failed(e.to_string()))?;
         Ok(())
     }
 
     async fn close(&self) -> fdo::Result<()> {
         self.sender
-            .send(Action::Close(None))
+            .send(Action::Close(None, CloseReason::Dismissed))
             .map_err(|e| fdo::Error::Failed(e.to_string()))?;
         Ok(())
     }
 
     async fn close_all(&self) -> fdo::Result<()> {
         self.sender
-            .send(Action::CloseAll)
+            .send(Action::CloseAll(CloseReason::Dismissed))
             .map_err(|e| fdo::Error::Failed(e.to_string()))?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn invoke_action(&self, id: u32, action_key: String) -> fdo::Result<()> {
+        self.sender
+            .send(Action::InvokeAction(id, action_key))
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Returns notifications timestamped within `[since, until]`, optionally narrowed by a
+    /// JSON-encoded [`NotificationFilter`] (an empty string means no filter), each serialized as
+    /// a JSON [`HistoryEntry`].
+    async fn history_query(
+        &self,
+        since: u64,
+        until: u64,
+        filter: String,
+    ) -> fdo::Result<Vec<String>> {
+        let filter = if filter.is_empty() {
+            None
+        } else {
+            Some(
+                serde_json::from_str::<NotificationFilter>(&filter)
+                    .map_err(|e| fdo::Error::Failed(format!("Invalid filter: {}", e)))?,
+            )
+        };
+        self.notifications
+            .query(since, until, filter.as_ref())
+            .iter()
+            .map(|notification| {
+                serde_json::to_string(&HistoryEntry::from(notification))
+                    .map_err(|e| fdo::Error::Failed(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns every notification added after `last_seq`, each serialized as a JSON
+    /// [`HistoryEntry`], along with the latest sequence number so the caller can pass it back on
+    /// its next call.
+    ///
+    /// Pull-side counterpart to the `NotificationAdded` signal, for clients (status bars,
+    /// scripts) that would rather catch up on demand than stay subscribed.
+    async fn poll_since(&self, last_seq: u64) -> fdo::Result<(u64, Vec<String>)> {
+        let (new_seq, notifications) = self.notifications.poll_since(last_seq);
+        let entries = notifications
+            .iter()
+            .map(|notification| {
+                serde_json::to_string(&HistoryEntry::from(notification))
+                    .map_err(|e| fdo::Error::Failed(e.to_string()))
+            })
+            .collect::<fdo::Result<Vec<String>>>()?;
+        Ok((new_seq, entries))
+    }
+}